@@ -134,7 +134,7 @@ impl WebtoonSub {
 
 
 impl Category {
-    fn from_str(cate: &str, sub: &str) -> Self {
+    pub fn from_str(cate: &str, sub: &str) -> Self {
         match cate.to_ascii_lowercase().as_str() {
             "同人志" | "doujinshi" | "trz" => match sub.to_ascii_lowercase().as_str() {
                 "全部" | "all" | "qb" => Self::DOUJINSHI(DoujinshiSub::ALL),
@@ -171,7 +171,7 @@ impl Category {
         }
     }
 
-    fn to_cate_info(&self) -> (String, &str) {
+    pub fn to_cate_info(&self) -> (String, &str) {
         match self {
             Self::DOUJINSHI(sub) => {
                 let cate_name = "同人志";
@@ -220,7 +220,7 @@ impl Category {
     }
 }
 
-fn build_cate_url(base_url: &str, cate_num: &str, page: i32) -> String {
+pub fn build_cate_url(base_url: &str, cate_num: &str, page: i32) -> String {
     format!(
         "{}/albums-index-page-{}-cate-{}.html",
         base_url.trim_end_matches('/'), // 防止双斜杠
@@ -269,17 +269,22 @@ pub async fn handle(
             "⬅️上一页",
             "cate",
             &[cate.clone(), sub.clone(), (page - 1).to_string()],
-        ));
+        )?);
     }
     buttons.push(encode_command_button(
         "下一页➡️",
         "cate",
         &[cate.clone(), sub.clone(), (page + 1).to_string()],
-    ));
+    )?);
+
+    let mut rows = vec![buttons];
+    if !mangas.is_empty() {
+        rows.push(super::zip::batch_buttons(mangas.iter().map(|m| m.id).collect()).await?);
+    }
 
     bot.send_message(msg.chat.id, text)
         .parse_mode(ParseMode::MarkdownV2)
-        .reply_markup(InlineKeyboardMarkup::new([buttons]))
+        .reply_markup(InlineKeyboardMarkup::new(rows))
         .await?;
 
     Ok(())
@@ -1,36 +1,43 @@
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{BotError, Result};
 use crate::models::MangaDetail;
 use crate::utils::codec::{encode_command_button, encode_command_link};
 use crate::utils::escape_md_v2;
 use crate::{services, utils};
 use std::format;
 use teloxide::prelude::*;
-use teloxide::types::InlineKeyboardMarkup;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 
 pub async fn handle(bot: &Bot, msg: &Message, config: &Config, aid: String) -> Result<()> {
+    let id = aid
+        .parse::<i64>()
+        .map_err(|_| BotError::InvalidCommand { reason: format!("invalid aid: {aid}") })?;
     let info_url = build_info_url(&config.manga.base_url, &aid);
-    let manga_detail = services::manga::parse_detail(
-        aid.parse::<i64>().unwrap(),
-        &info_url,
-        &config.manga.base_url,
-    )
-    .await?;
-    let detail_msg = build_detail_msg(manga_detail, &config.bot.bot_name).await;
+    let manga_detail = services::manga::parse_detail(id, &info_url, &config.manga.base_url).await?;
+    let detail_msg = build_detail_msg(manga_detail, config).await;
 
     let mut buttons = Vec::with_capacity(2);
-    buttons.push(encode_command_button("🏞️预览", "preview", &[aid.clone()]));
-    buttons.push(encode_command_button("⏬下载️", "zip", &[aid]));
+    buttons.push(encode_command_button("🏞️预览", "preview", &[aid.clone()])?);
+    buttons.push(encode_command_button("⏬下载️", "zip", &[aid])?);
+
+    let mut keyboard = vec![buttons];
+    // 默认的 localhost 地址对用户不可达，Telegram 也会拒绝这类按钮
+    if services::links::web_host_public(config)
+        && let Ok(url) = reqwest::Url::parse(&services::links::reader_url(config, id))
+    {
+        keyboard.push(vec![InlineKeyboardButton::url("📖在线阅读", url)]);
+    }
 
     bot.send_message(msg.chat.id, detail_msg)
         .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-        .reply_markup(InlineKeyboardMarkup::new([buttons]))
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
         .await?;
 
     Ok(())
 }
 
-async fn build_detail_msg(m: MangaDetail, bot_name: &str) -> String {
+async fn build_detail_msg(m: MangaDetail, config: &Config) -> String {
+    let bot_name = config.bot.bot_name.as_str();
     let title = escape_md_v2(&m.title);
     let author = escape_md_v2(&m.author);
     let author_key_num = utils::cache::search_key_to_num(&author).await;
@@ -42,7 +49,7 @@ async fn build_detail_msg(m: MangaDetail, bot_name: &str) -> String {
 
     let category = escape_md_v2(&m.category);
     let desc = escape_md_v2(&m.description);
-    let cover_url = &m.cover;
+    let cover_url = services::proxy::public_proxy_url(config, &m.cover);
 
     let tags = futures::future::join_all(m.tags.iter().map(|t| {
         let tag = escape_md_v2(t);
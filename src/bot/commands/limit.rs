@@ -0,0 +1,47 @@
+use crate::error::Result;
+use crate::utils::{self, client};
+use teloxide::prelude::*;
+
+fn parse_rate(s: &str) -> Option<u64> {
+    match s.to_ascii_lowercase().as_str() {
+        "off" | "none" => Some(0),
+        s => utils::parse_bytes(s),
+    }
+}
+
+fn rate_text(rate: u64) -> String {
+    if rate == 0 { "不限速".to_string() } else { format!("{}/s", utils::human_bytes(rate)) }
+}
+
+/// 查看或调整下载带宽：/limit <global> <job>，只给一个值时只调整全局
+pub async fn handle(
+    bot: &Bot,
+    msg: &Message,
+    global: Option<String>,
+    job: Option<String>,
+) -> Result<()> {
+    let parsed = [&global, &job].map(|arg| arg.as_deref().map(|s| (s, parse_rate(s))));
+    if let Some((arg, _)) = parsed.iter().flatten().find(|(_, rate)| rate.is_none()) {
+        bot.send_message(msg.chat.id, format!("❌ 无效的速率: {}（示例: 512K, 2M, off）", arg))
+            .await?;
+        return Ok(());
+    }
+
+    let [global, job] = parsed.map(|arg| arg.and_then(|(_, rate)| rate));
+    if let Some(rate) = global {
+        client::bandwidth().set_rate(rate);
+    }
+    if let Some(rate) = job {
+        client::set_job_bandwidth(rate);
+    }
+
+    let job_rate = client::job_bandwidth().rate();
+    let text = format!(
+        "{}下载带宽\n全局: {}\n单任务: {}",
+        if global.is_some() || job.is_some() { "✅ 已调整" } else { "📶 当前" },
+        rate_text(client::bandwidth().rate()),
+        rate_text(job_rate)
+    );
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
@@ -78,7 +78,7 @@ pub async fn handle(bot: &Bot, msg: &Message, menu_type: MenuType) -> Result<()>
 
     let mut buttons = Vec::with_capacity(callbacks.len());
     for (text, command, args) in callbacks {
-        buttons.push(encode_command_button(&text, &command, args.as_slice()));
+        buttons.push(encode_command_button(&text, &command, args.as_slice())?);
     }
 
     bot.send_message(msg.chat.id, menu_type.as_str()).reply_markup(InlineKeyboardMarkup::new([buttons])).await?;
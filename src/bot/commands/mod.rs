@@ -1,3 +1,5 @@
+use crate::utils::archive::ArchiveFormat;
+use crate::utils::pages::is_range_spec;
 use teloxide::utils::command::BotCommands;
 use teloxide::utils::command::ParseError;
 
@@ -27,6 +29,47 @@ fn parse_string_string_i32(
     Ok((cate, sub, page))
 }
 
+/// /zip 的参数
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ZipArgs {
+    pub aids: Vec<i64>,
+    /// 页码区间，只能用于单部作品
+    pub pages: Option<String>,
+    pub format: Option<String>,
+    pub transcode: Option<String>,
+    /// 多部作品合并成一个压缩包（每部作品一个目录）
+    pub combine: bool,
+}
+
+fn parse_zip_args(s: String) -> Result<(ZipArgs,), ParseError> {
+    let mut args = ZipArgs::default();
+    // 各类参数的取值互不重叠，顺序不限：纯数字为 aid，其余不是打包格式、页码区间或 merge 的当作转码方式
+    for arg in s.split_whitespace() {
+        if let Ok(aid) = arg.parse::<i64>() {
+            args.aids.push(aid);
+        } else if matches!(arg.to_ascii_lowercase().as_str(), "merge" | "combine" | "合并") {
+            args.combine = true;
+        } else if args.pages.is_none() && is_range_spec(arg) {
+            args.pages = Some(arg.to_string());
+        } else if args.format.is_none() && ArchiveFormat::parse(arg).is_some() {
+            args.format = Some(arg.to_string());
+        } else if args.transcode.is_none() {
+            args.transcode = Some(arg.to_string());
+        }
+    }
+
+    Ok((args,))
+}
+
+fn parse_string_string(s: String) -> Result<(Option<String>, Option<String>), ParseError> {
+    let mut args = s.split_whitespace();
+
+    let first = args.next().map(|s| s.to_string());
+    let second = args.next().map(|s| s.to_string());
+
+    Ok((first, second))
+}
+
 fn parse_start_payload(s: String) -> Result<(Option<String>,), ParseError> {
     let s = s.trim();
     if s.is_empty() { Ok((None,)) } else { Ok((Some(s.to_string()),)) }
@@ -70,8 +113,33 @@ pub enum Command {
     #[command(description = "预览漫画: /preview <aid> <page>", parse_with = parse_string_i32)]
     Preview(Option<String>, Option<i32>),
 
-    #[command(description = "下载漫画: /zip <aid>")]
-    Zip(i64),
+    #[command(
+        description = "下载漫画: /zip <aid...> <pages> <format> <image> <merge>\n\
+                   aid: 可填多个，逐部打包\n\
+                   pages: 页码区间如 1-20,35（单页写作 p35），仅限单部作品\n\
+                   format: zip（默认）, cbz, epub, pdf\n\
+                   image: original（默认）, jpeg, png\n\
+                   merge: 多部作品合并为一个 zip，每部一个目录",
+        parse_with = parse_zip_args
+    )]
+    Zip(ZipArgs),
+
+    #[command(description = "在线打包下载（不占用服务器磁盘）: /stream <aid>")]
+    Stream(i64),
+
+    #[command(description = "查看下载队列: /jobs")]
+    Jobs,
+
+    #[command(description = "取消下载: /cancel <job>")]
+    Cancel(String),
+
+    #[command(
+        description = "下载限速: /limit <global> <job>\n\
+                   global: 全局带宽，如 2M, 512K, off\n\
+                   job: 单任务带宽（可选），不带参数时查看当前设置",
+        parse_with = parse_string_string
+    )]
+    Limit(Option<String>, Option<String>),
 
     #[command(description = "显示排行榜菜单: /menu_rank")]
     Menu_Rank,
@@ -89,8 +157,33 @@ pub enum Command {
     Menu_Cate_HM,
 }
 
+impl Command {
+    /// 命令名，与 Telegram 中的命令一致
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Start(_) => "start",
+            Self::Search(..) => "search",
+            Self::Rank(..) => "rank",
+            Self::Cate(..) => "cate",
+            Self::Info(_) => "info",
+            Self::Preview(..) => "preview",
+            Self::Zip(_) => "zip",
+            Self::Stream(_) => "stream",
+            Self::Jobs => "jobs",
+            Self::Cancel(_) => "cancel",
+            Self::Limit(..) => "limit",
+            Self::Menu_Rank => "menu_rank",
+            Self::Menu_Cate_TRZ => "menu_cate_trz",
+            Self::Menu_Cate_DXB => "menu_cate_dxb",
+            Self::Menu_Cate_DP => "menu_cate_dp",
+            Self::Menu_Cate_HM => "menu_cate_hm",
+        }
+    }
+}
+
 pub mod cate;
 pub mod info;
+pub mod limit;
 pub mod preview;
 pub mod rank;
 pub mod search;
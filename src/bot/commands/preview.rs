@@ -30,7 +30,10 @@ pub async fn handle(
         .into_iter()
         .map(|url| {
             InputMedia::Photo(InputMediaPhoto {
-                media: InputFile::url(url.parse().unwrap()), // 关键：用 url()
+                // 经由图片代理，Telegram 直接拉取源站图片常会失败
+                media: InputFile::url(
+                    services::proxy::public_proxy_url(config, url).parse().unwrap(),
+                ),
                 caption: None,
                 parse_mode: None,
                 caption_entities: None,
@@ -46,7 +49,7 @@ pub async fn handle(
             "下一页➡️",
             "preview",
             &[paid, (page.unwrap() + 1).to_string()],
-        )];
+        )?];
         bot.send_message(msg.chat.id, "🌏浏览更多图片")
             .reply_markup(InlineKeyboardMarkup::new([buttons]))
             .await?;
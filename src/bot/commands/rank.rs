@@ -32,7 +32,8 @@ impl RankType {
         }
     }
 
-    fn from_str(s: &str) -> Option<Self> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
         match s.to_ascii_lowercase().as_str() {
             "day" | "d" | "1" => Some(Self::Day),
             "week" | "w" | "2" => Some(Self::Week),
@@ -42,7 +43,7 @@ impl RankType {
     }
 }
 
-fn build_ranking_url(base_url: &str, rank_type: RankType, page: i32) -> String {
+pub fn build_ranking_url(base_url: &str, rank_type: RankType, page: i32) -> String {
     format!(
         "{}/albums-favorite_ranking-page-{}-type-{}.html",
         base_url.trim_end_matches('/'), // 防止双斜杠
@@ -113,17 +114,22 @@ pub async fn handle(
             "⬅️上一页",
             "rank",
             &[period.clone(), (page - 1).to_string()],
-        ));
+        )?);
     }
     buttons.push(encode_command_button(
         "下一页➡️",
         "rank",
         &[period.clone(), (page + 1).to_string()],
-    ));
+    )?);
+
+    let mut rows = vec![buttons];
+    if !mangas.is_empty() {
+        rows.push(super::zip::batch_buttons(mangas.iter().map(|m| m.id).collect()).await?);
+    }
 
     bot.send_message(msg.chat.id, text)
         .parse_mode(ParseMode::MarkdownV2)
-        .reply_markup(InlineKeyboardMarkup::new([buttons]))
+        .reply_markup(InlineKeyboardMarkup::new(rows))
         .await?;
 
     Ok(())
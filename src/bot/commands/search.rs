@@ -48,17 +48,22 @@ pub async fn handle(
             "⬅️上一页",
             "csearch",
             &[key_num.to_string(), typ.clone(), (page - 1).to_string()],
-        ));
+        )?);
     }
     buttons.push(encode_command_button(
         "下一页➡️",
         "csearch",
         &[key_num.to_string(), typ.clone(), (page + 1).to_string()],
-    ));
+    )?);
+
+    let mut rows = vec![buttons];
+    if !mangas.is_empty() {
+        rows.push(super::zip::batch_buttons(mangas.iter().map(|m| m.id).collect()).await?);
+    }
 
     bot.send_message(msg.chat.id, text)
         .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-        .reply_markup(InlineKeyboardMarkup::new([buttons]))
+        .reply_markup(InlineKeyboardMarkup::new(rows))
         .await?;
 
     Ok(())
@@ -72,7 +77,7 @@ fn type_nav(typ: &str, key: &str) -> String {
     }
 }
 
-fn build_search_url(base_url: &str, key: &str, typ: &str, page: i32) -> String {
+pub fn build_search_url(base_url: &str, key: &str, typ: &str, page: i32) -> String {
     let search_key = percent_encoding::utf8_percent_encode(key, percent_encoding::NON_ALPHANUMERIC);
     match typ {
         "u" => format!(
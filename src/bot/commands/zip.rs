@@ -1,123 +1,1155 @@
+use crate::bot::commands::ZipArgs;
 use crate::error::{BotError, Result};
+use crate::models::{ArchiveStream, MangaDetail};
+use crate::services::jobs::{self, DownloadOptions, Enqueued, Job, JobStatus, Subscriber};
+use crate::services::scheduler::{Position, scheduler};
+use crate::services::shutdown;
+use crate::utils::archive::{ArchiveFormat, ArchiveSource};
+use crate::utils::codec::encode_command_button;
+use crate::utils::http::{DownloadProgress, DownloadReport};
+use crate::utils::img::Transcode;
 use crate::{services, utils};
 use std::format;
+use std::time::{Duration, Instant};
 use teloxide::prelude::*;
-use teloxide::types::{InputFile, MessageId};
-use tracing::error;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile, MessageId};
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
 
 static DOC_LIMIT_SIZE: u64 = 50 * 1024 * 1024;
+/// 分卷时为压缩包目录、元数据等预留的空间
+static VOLUME_MARGIN: u64 = 2 * 1024 * 1024;
+static PROGRESS_INTERVAL: Duration = Duration::from_secs(3);
+static PROGRESS_BAR_WIDTH: usize = 16;
+static JOB_LIST_LIMIT: usize = 20;
+/// 批量下载一次最多包含的作品数，与结果列表显示的条数一致
+static BATCH_LIMIT: usize = 20;
 
 pub async fn handle(
     bot: &Bot,
     msg: &Message,
+    user: UserId,
     config: &crate::config::Config,
-    aid: i64,
+    args: ZipArgs,
 ) -> Result<()> {
-    if 0 == aid {
+    let aids: Vec<i64> = args.aids.iter().copied().filter(|&aid| aid != 0).collect();
+    if aids.is_empty() {
         return Err(BotError::ParseError("aid is required or parse error".to_string()));
     }
-    let sid = aid.to_string();
+    let combine = args.combine && aids.len() > 1;
+    let format = match args.format {
+        Some(f) => ArchiveFormat::parse(&f).ok_or_else(|| BotError::InvalidCommand {
+            reason: format!("unsupported format: {}", f),
+        })?,
+        // 合并打包只支持 zip
+        None if combine => ArchiveFormat::Zip,
+        None => config.server.archive_format,
+    };
+    let transcode = match args.transcode {
+        Some(t) => Transcode::parse(&t).ok_or_else(|| BotError::InvalidCommand {
+            reason: format!("unsupported image format: {}", t),
+        })?,
+        None => config.server.transcode,
+    };
+    let options = DownloadOptions { format, transcode, pages: None };
 
-    let info_url = super::info::build_info_url(&config.manga.base_url, &sid);
-    let info = services::manga::parse_detail(aid, &info_url, &config.manga.base_url).await?;
-    let images_url = crate::bot::commands::build_images_url(&config.manga.base_url, &sid);
-    let images =
-        services::manga::extract_image_urls(&sid, &images_url, &config.manga.base_url).await?;
-    if images.is_empty() {
-        return Err(BotError::ParseError(format!("no images found for aid {}", aid)));
+    if aids.len() == 1 {
+        return handle_work(bot, msg, user, config, aids[0], options, args.pages).await;
+    }
+    if args.pages.is_some() {
+        return Err(BotError::InvalidCommand {
+            reason: "page ranges only apply to a single work".to_string(),
+        });
+    }
+    if combine {
+        if format != ArchiveFormat::Zip {
+            return Err(BotError::InvalidCommand {
+                reason: format!("merged archives only support zip, got {}", format.extension()),
+            });
+        }
+        return handle_combined(bot, msg, user, config, aids, options).await;
+    }
+
+    // 逐部入队，单部作品出错不影响其余作品
+    for aid in aids {
+        if let Err(e) = handle_work(bot, msg, user, config, aid, options.clone(), None).await {
+            error!(aid, error = %e, "批量下载入队失败");
+            bot.send_message(msg.chat.id, format!("❌ {} 下载失败: {}", aid, e)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 下载单部作品，`pages` 为用户指定的页码区间
+async fn handle_work(
+    bot: &Bot,
+    msg: &Message,
+    user: UserId,
+    config: &crate::config::Config,
+    aid: i64,
+    mut options: DownloadOptions,
+    pages: Option<String>,
+) -> Result<()> {
+    let (info, images) = fetch_work(config, aid).await?;
+    if let Some(spec) = pages {
+        let selected = utils::pages::parse_ranges(&spec, images.len()).ok_or_else(|| {
+            BotError::InvalidCommand { reason: format!("invalid page range: {}", spec) }
+        })?;
+        if selected.is_empty() {
+            return Err(BotError::InvalidCommand {
+                reason: format!("page range {} is out of 1-{}", spec, images.len()),
+            });
+        }
+        // 选中全部页面时等同于整部下载，可与整部下载的任务和压缩包复用
+        if selected.len() < images.len() {
+            options.pages = Some(utils::pages::format_ranges(&selected).replace(", ", ","));
+        }
+    }
+    let format = options.format;
+    let transcode = options.transcode;
+    let selected = select_images(images.clone(), &options);
+
+    // 已有完整且可读的压缩包时直接复用，不再重复下载
+    let zip_path = archive_path(config, &info.title, &options);
+    let verified = tokio::task::spawn_blocking({
+        let zip_path = zip_path.clone();
+        let pages = selected.len();
+        move || utils::archive::verify(format, &zip_path, pages)
+    })
+    .await
+    .unwrap_or(false);
+    if verified {
+        info!(aid, path = %zip_path, "复用已打包的压缩包");
+        // 分卷需要原始页面，页面不全时只能发送下载链接
+        let manga_dir = format!("{}/{}", config.server.download_path, info.title);
+        let pages: Vec<String> = selected
+            .iter()
+            .map(|(i, _)| utils::http::find_page(&manga_dir, *i, images.len()))
+            .map(|page| utils::img::find_transcoded(&page?, transcode))
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        let source_url = super::info::build_info_url(&config.manga.base_url, &aid.to_string());
+        let files =
+            split_if_oversize(config, &options, &zip_path, &info, pages, source_url, None).await;
+        // 复用也算一次下载，避免被保留策略当作最久未用的作品清理；
+        // 分卷在压缩包之后更新，下次仍可复用
+        for path in std::iter::once(&zip_path).chain(files.iter().filter(|f| **f != zip_path)) {
+            if let Err(e) = utils::fs::touch(std::path::Path::new(path)) {
+                warn!(path = %path, error = %e, "更新压缩包修改时间失败");
+            }
+        }
+        let retry = retry_markup(aid, &options).await?;
+        return deliver_archive(bot, config, &[msg.chat.id], &files, &info.title, None, retry)
+            .await;
     }
 
+    let mut job = Job::new(aid, msg.chat.id.0, 0, info.title.clone(), selected.len(), options);
+    job.user_id = user.0;
+    enqueue_job(bot, msg, config, job, vec![(info, images)]).await
+}
+
+/// 合并打包多部作品：整批作为一个任务排队，完成后推送一个每部作品一个目录的压缩包
+async fn handle_combined(
+    bot: &Bot,
+    msg: &Message,
+    user: UserId,
+    config: &crate::config::Config,
+    aids: Vec<i64>,
+    options: DownloadOptions,
+) -> Result<()> {
+    let works = fetch_works(config, &aids).await?;
+    let title = format!("{} 等{}部", works[0].0.title, works.len());
+    let total = works.iter().map(|(_, images)| images.len()).sum();
+
+    let mut job = Job::new(aids[0], msg.chat.id.0, 0, title, total, options);
+    job.user_id = user.0;
+    job.works = aids;
+    job.work_titles = works.iter().map(|(detail, _)| detail.title.clone()).collect();
+    enqueue_job(bot, msg, config, job, works).await
+}
+
+/// 发送状态消息并登记任务；已有相同任务时并入该任务，否则启动下载
+async fn enqueue_job(
+    bot: &Bot,
+    msg: &Message,
+    config: &crate::config::Config,
+    mut job: Job,
+    works: Vec<(MangaDetail, Vec<String>)>,
+) -> Result<()> {
+    let title = job.title.clone();
     let reply_msg = bot
         .send_message(
             msg.chat.id,
             format!(
                 "【{}】\n\n {}",
-                utils::escape_md_v2(&info.title),
+                utils::escape_md_v2(&title),
                 utils::escape_md_v2("⬇️后台下载中，稍后推送...")
             ),
         )
         .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+        .reply_markup(cancel_markup(&job.id)?)
         .await?;
+    job.status_msg_id = reply_msg.id.0;
 
-    let bot_clone = bot.clone();
-    let chat_id = msg.chat.id;
-    let reply_msg_id = reply_msg.id;
-    let title = info.title.clone();
-    let images_owned = images; // 转移所有权
-    let config_clone = config.clone();
+    match jobs::store().insert_or_join(job).await? {
+        Enqueued::Created(job) => spawn_job(bot.clone(), job, works, config.clone()),
+        Enqueued::Joined(existing) => {
+            info!(job = %existing.id, aid = existing.aid, "合并到进行中的下载任务");
+            bot.edit_message_text(
+                msg.chat.id,
+                reply_msg.id,
+                format!(
+                    "【{}】\n\n {}",
+                    utils::escape_md_v2(&title),
+                    utils::escape_md_v2("⏳已有相同作品在下载，完成后一并推送...")
+                ),
+            )
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .await?;
+        }
+    }
 
-    tokio::spawn(async move {
-        let result = download_task(
-            bot_clone.clone(),
-            chat_id,
-            reply_msg_id,
-            title,
-            images_owned,
-            &config_clone,
-        )
-        .await;
+    Ok(())
+}
+
+/// 启动时恢复上次未完成的下载任务，并向原会话报告
+pub async fn resume_jobs(bot: &Bot, config: &crate::config::Config) {
+    for job in jobs::store().list().await {
+        // 关闭开始后剩余任务留在任务日志中，下次启动再恢复
+        if shutdown::token().is_cancelled() {
+            break;
+        }
+        info!(job = %job.id, aid = job.aid, "恢复未完成的下载任务");
+        let aids = if job.works.is_empty() { vec![job.aid] } else { job.works.clone() };
+        let works = match fetch_works(config, &aids).await {
+            Ok(work) => work,
+            Err(e) => {
+                error!(job = %job.id, aid = job.aid, error = %e, "恢复任务失败：无法获取作品信息");
+                let _ = bot
+                    .send_message(
+                        ChatId(job.chat_id),
+                        format!("❌ 服务重启后恢复下载失败: {}", job.title),
+                    )
+                    .await;
+                let _ = jobs::store().remove(&job.id).await;
+                continue;
+            }
+        };
 
-        if let Err(e) = result {
-            error!("后台下载任务失败: {:?}", e);
-            // 发送错误消息
-            let _ = bot_clone
-                .send_message(chat_id, format!("下载失败: {:?}", e))
+        let text = format!(
+            "【{}】\n\n {}",
+            utils::escape_md_v2(&job.title),
+            utils::escape_md_v2("♻️服务已重启，继续后台下载中...")
+        );
+        let edited = bot
+            .edit_message_text(ChatId(job.chat_id), MessageId(job.status_msg_id), text.clone())
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .reply_markup(cancel_markup(&job.id).unwrap_or_default())
+            .await;
+
+        let mut job = job;
+        if !job.works.is_empty() && job.work_titles.is_empty() {
+            // 旧版本的任务日志没有记录各作品标题
+            job.work_titles = works.iter().map(|(detail, _)| detail.title.clone()).collect();
+            let titles = job.work_titles.clone();
+            let _ = jobs::store().update(&job.id, |j| j.work_titles = titles).await;
+        }
+        if edited.is_err() {
+            // 原提示消息已不可编辑，重新发送一条
+            if let Ok(m) = bot
+                .send_message(ChatId(job.chat_id), text.clone())
+                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                .reply_markup(cancel_markup(&job.id).unwrap_or_default())
+                .await
+            {
+                job.status_msg_id = m.id.0;
+                let _ = jobs::store().update(&job.id, |j| j.status_msg_id = m.id.0).await;
+            }
+        }
+        for sub in &job.followers {
+            let _ = bot
+                .edit_message_text(ChatId(sub.chat_id), MessageId(sub.status_msg_id), text.clone())
                 .parse_mode(teloxide::types::ParseMode::MarkdownV2)
                 .await;
         }
-    });
 
+        spawn_job(bot.clone(), job, works, config.clone());
+    }
+}
+
+/// 获取作品详情及全部页面地址
+async fn fetch_work(
+    config: &crate::config::Config,
+    aid: i64,
+) -> Result<(MangaDetail, Vec<String>)> {
+    let sid = aid.to_string();
+
+    let info_url = super::info::build_info_url(&config.manga.base_url, &sid);
+    let info = services::manga::parse_detail(aid, &info_url, &config.manga.base_url).await?;
+    let images_url = crate::bot::commands::build_images_url(&config.manga.base_url, &sid);
+    let images =
+        services::manga::extract_image_urls(&sid, &images_url, &config.manga.base_url).await?;
+    if images.is_empty() {
+        return Err(BotError::ParseError(format!("no images found for aid {}", aid)));
+    }
+
+    Ok((info, images))
+}
+
+async fn fetch_works(
+    config: &crate::config::Config,
+    aids: &[i64],
+) -> Result<Vec<(MangaDetail, Vec<String>)>> {
+    let mut works = Vec::with_capacity(aids.len());
+    for &aid in aids {
+        works.push(fetch_work(config, aid).await?);
+    }
+    Ok(works)
+}
+
+/// 按任务选项挑出要下载的页面 (页序, url)
+fn select_images(images: Vec<String>, options: &DownloadOptions) -> Vec<(usize, String)> {
+    let selected =
+        options.pages.as_deref().and_then(|p| utils::pages::parse_ranges(p, images.len()));
+    images
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.as_ref().is_none_or(|s| s.binary_search(&(i + 1)).is_ok()))
+        .collect()
+}
+
+/// 在线打包下载: /stream <aid>，返回边抓取边打包的链接，不占用服务器磁盘
+pub async fn stream(
+    bot: &Bot,
+    msg: &Message,
+    config: &crate::config::Config,
+    aid: i64,
+) -> Result<()> {
+    if 0 == aid {
+        return Err(BotError::ParseError("aid is required or parse error".to_string()));
+    }
+    let (info, images) = fetch_work(config, aid).await?;
+
+    let token = uuid::Uuid::new_v4().to_string();
+    let target = ArchiveStream { title: info.title.clone(), images };
+    utils::cache::stream_token_cache().insert(token.clone(), target).await;
+
+    let text = format!(
+        "[点击下载⬇️ {}]({})\n\n{}",
+        utils::escape_md_v2(&info.title),
+        download_url(config, &token),
+        utils::escape_md_v2(&format!(
+            "📦 边下载边打包，链接 {} 分钟内有效",
+            config.server.cache_stream_token_minute_ttl
+        ))
+    );
+    bot.send_message(msg.chat.id, text).parse_mode(teloxide::types::ParseMode::MarkdownV2).await?;
     Ok(())
 }
 
-async fn download_task(
+fn download_url(config: &crate::config::Config, token: &str) -> String {
+    let host = config.server.web_host.trim_end_matches('/');
+    format!("{}/download?token={}", host, token)
+}
+
+/// 取消下载任务: /cancel <job>
+pub async fn cancel(bot: &Bot, msg: &Message, job_id: String) -> Result<()> {
+    let job_id = job_id.trim();
+    if job_id.is_empty() {
+        return Err(BotError::InvalidCommand { reason: "job id is required".to_string() });
+    }
+
+    if !jobs::store().cancel(job_id) {
+        bot.send_message(msg.chat.id, "⚠️ 任务不存在或已结束").await?;
+    }
+
+    Ok(())
+}
+
+/// 列出排队中和运行中的下载任务
+pub async fn list_jobs(bot: &Bot, msg: &Message) -> Result<()> {
+    let jobs = jobs::store().list().await;
+    if jobs.is_empty() {
+        bot.send_message(msg.chat.id, "📭 当前没有下载任务").await?;
+        return Ok(());
+    }
+
+    let mut lines = Vec::with_capacity(jobs.len() + 1);
+    lines.push(format!("*下载任务* 📄{}", jobs.len()));
+    let mut buttons = Vec::with_capacity(jobs.len());
+    for job in jobs.iter().take(JOB_LIST_LIMIT) {
+        let state = match scheduler().position(&job.id) {
+            Some(Position::Queued(n)) => format!("⏳排队中 第{}位", n),
+            _ => match job.status {
+                JobStatus::Queued => "⏳等待中".to_string(),
+                JobStatus::Downloading => format!("⬇️下载中 {}/{}", job.done, job.total),
+                JobStatus::Transcoding => "🔄转码中".to_string(),
+                JobStatus::Archiving => "📦打包中".to_string(),
+                JobStatus::Sending => "📤发送中".to_string(),
+            },
+        };
+        lines.push(format!(
+            "\\* 【{}】 {} `{}`",
+            utils::escape_md_v2(&job.title),
+            utils::escape_md_v2(&state),
+            job.id
+        ));
+        buttons.push(vec![encode_command_button(
+            &format!("❌取消 {}", job.title.chars().take(16).collect::<String>()),
+            "cancel",
+            &[job.id.as_str()],
+        )?]);
+    }
+
+    bot.send_message(msg.chat.id, lines.join("\n"))
+        .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+        .reply_markup(InlineKeyboardMarkup::new(buttons))
+        .await?;
+    Ok(())
+}
+
+fn cancel_markup(job_id: &str) -> Result<InlineKeyboardMarkup> {
+    Ok(InlineKeyboardMarkup::new([[encode_command_button("❌取消", "cancel", &[job_id])?]]))
+}
+
+/// 重试按钮，参数放进缓存，只在回调数据中携带编号
+async fn retry_markup(aid: i64, options: &DownloadOptions) -> Result<InlineKeyboardMarkup> {
+    let mut args = vec![
+        aid.to_string(),
+        options.format.extension().to_string(),
+        options.transcode.as_str().to_string(),
+    ];
+    args.extend(options.pages.clone());
+    let num = utils::cache::retry_to_num(args).await;
+    Ok(InlineKeyboardMarkup::new([[encode_command_button("🔁重试", "rezip", &[num.to_string()])?]]))
+}
+
+/// 合并打包任务的重试按钮，作品列表放进缓存，只在回调数据中携带编号
+async fn combined_retry_markup(job: &Job) -> Result<InlineKeyboardMarkup> {
+    let num = utils::cache::batch_to_num(job.works.clone()).await;
+    let args = [num.to_string(), "merge".to_string(), job.options.transcode.as_str().to_string()];
+    Ok(InlineKeyboardMarkup::new([[encode_command_button("🔁重试", "zipall", &args)?]]))
+}
+
+/// 结果列表底部的批量下载按钮：逐部打包，或合并为一个压缩包；只包含前 `BATCH_LIMIT` 部
+pub async fn batch_buttons(aids: Vec<i64>) -> Result<Vec<InlineKeyboardButton>> {
+    let aids = aids.into_iter().take(BATCH_LIMIT).collect();
+    let num = utils::cache::batch_to_num(aids).await.to_string();
+    Ok(vec![
+        encode_command_button("📦全部下载", "zipall", &[num.as_str()])?,
+        encode_command_button("🗂合并下载", "zipall", &[num.as_str(), "merge"])?,
+    ])
+}
+
+fn spawn_job(
     bot: Bot,
-    chat_id: ChatId,
-    reply_msg_id: MessageId,
-    title: String,
+    job: Job,
+    works: Vec<(MangaDetail, Vec<String>)>,
+    config: crate::config::Config,
+) {
+    tokio::spawn(async move {
+        let _running = shutdown::track();
+        let token = jobs::store().cancel_token(&job.id);
+        let mut ticket = scheduler().enqueue(&job.id, job.owner());
+        // 有空闲名额时直接启动，只有确实需要等待时才提示排队
+        let started = ticket.try_start();
+        if started.is_none()
+            && let Some(Position::Queued(n)) = scheduler().position(&job.id)
+        {
+            let text = format!(
+                "【{}】\n\n{}",
+                utils::escape_md_v2(&job.title),
+                utils::escape_md_v2(&format!("⏳排队中，前面还有 {} 个任务...", n - 1))
+            );
+            let _ = bot
+                .edit_message_text(ChatId(job.chat_id), MessageId(job.status_msg_id), text)
+                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                .reply_markup(cancel_markup(&job.id).unwrap_or_default())
+                .await;
+        }
+
+        let result = tokio::select! {
+            r = async {
+                // 关闭时排队中的任务不再启动，保留在任务日志中
+                let _permit = match started {
+                    Some(permit) => permit,
+                    None => tokio::select! {
+                        permit = ticket.wait() => permit,
+                        _ = shutdown::token().cancelled() => return Err(BotError::ShuttingDown),
+                    },
+                };
+                match works.as_slice() {
+                    [(detail, images)] if job.works.is_empty() => {
+                        download_task(&bot, &job, detail, images.clone(), &config).await
+                    }
+                    works => combined_task(&bot, &job, works, &config).await,
+                }
+            } => r,
+            _ = token.cancelled() => Err(BotError::Cancelled),
+            _ = shutdown::deadline().cancelled() => Err(BotError::ShuttingDown),
+        };
+
+        // 任务期间可能有新的请求者加入，以最新记录为准
+        let subscribers = current_subscribers(&job).await;
+        match result {
+            Ok(()) => {}
+            Err(BotError::ShuttingDown) => {
+                // 保留任务日志与已下载的页面，重启后由 resume_jobs 继续
+                info!(job = %job.id, aid = job.aid, "服务关闭，下载任务已保存");
+                for sub in &subscribers {
+                    let _ = bot
+                        .edit_message_text(
+                            ChatId(sub.chat_id),
+                            MessageId(sub.status_msg_id),
+                            format!("【{}】\n\n⏸ 服务正在重启，恢复后自动继续下载", job.title),
+                        )
+                        .await;
+                }
+                return;
+            }
+            Err(BotError::Cancelled) => {
+                info!(job = %job.id, aid = job.aid, "下载任务已取消");
+                let titles: Vec<&str> = works.iter().map(|(d, _)| d.title.as_str()).collect();
+                remove_partial_files(&config, &job, &titles).await;
+                for sub in &subscribers {
+                    let _ = bot
+                        .edit_message_text(
+                            ChatId(sub.chat_id),
+                            MessageId(sub.status_msg_id),
+                            format!("【{}】\n\n🚫 下载已取消", job.title),
+                        )
+                        .await;
+                }
+            }
+            Err(e) => {
+                error!("后台下载任务失败: {:?}", e);
+                // 发送错误消息
+                for sub in &subscribers {
+                    let _ = bot
+                        .send_message(ChatId(sub.chat_id), format!("下载失败: {:?}", e))
+                        .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                        .await;
+                }
+            }
+        }
+
+        if let Err(e) = jobs::store().remove(&job.id).await {
+            error!(job = %job.id, error = %e, "任务日志更新失败");
+        }
+    });
+}
+
+/// 清理被中断的下载留下的临时文件；同名作品还有其他任务在下载时不动
+async fn remove_partial_files(config: &crate::config::Config, job: &Job, titles: &[&str]) {
+    let others = jobs::store().list().await;
+    for title in titles {
+        if others.iter().any(|j| j.id != job.id && j.titles().iter().any(|t| t == title)) {
+            continue;
+        }
+
+        let manga_dir = format!("{}/{}", config.server.download_path, title);
+        let Ok(mut entries) = tokio::fs::read_dir(&manga_dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "part") {
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
+    }
+}
+
+fn archive_path(config: &crate::config::Config, title: &str, options: &DownloadOptions) -> String {
+    format!(
+        "{}/{}.{}",
+        config.server.download_path,
+        archive_stem(title, options),
+        options.format.extension()
+    )
+}
+
+/// 分卷文件路径，如 "title (1of3).zip"
+fn volume_path(
+    config: &crate::config::Config,
+    title: &str,
+    options: &DownloadOptions,
+    index: usize,
+    count: usize,
+) -> String {
+    format!(
+        "{}/{} ({}of{}).{}",
+        config.server.download_path,
+        archive_stem(title, options),
+        index,
+        count,
+        options.format.extension()
+    )
+}
+
+/// 打包文件名（不含扩展名），转码过的作品带上转码方式以免与原图版本混用，如 "title [jpeg]"；
+/// 只下载部分页面时再带上页码区间，如 "title [p1-20,35]"
+fn archive_stem(title: &str, options: &DownloadOptions) -> String {
+    let mut stem = match options.transcode {
+        Transcode::Original => title.to_string(),
+        t => format!("{} [{}]", title, t.as_str()),
+    };
+    if let Some(pages) = &options.pages {
+        stem.push_str(&format!(" [p{}]", pages));
+    }
+    stem
+}
+
+/// 压缩包超过文档上限且开启分卷时，按页拆成若干个独立的分卷，返回需要推送的文件；
+/// 已有比压缩包新且完整的分卷时直接复用；未开启、无需拆分或拆分失败时返回原压缩包
+async fn split_if_oversize(
+    config: &crate::config::Config,
+    options: &DownloadOptions,
+    archive: &str,
+    detail: &MangaDetail,
+    pages: Vec<String>,
+    source_url: String,
+    cover: Option<Vec<u8>>,
+) -> Vec<String> {
+    let whole = vec![archive.to_string()];
+    let oversize = tokio::fs::metadata(archive).await.is_ok_and(|m| m.len() >= DOC_LIMIT_SIZE);
+    if !config.server.split_volumes || !oversize || pages.is_empty() {
+        return whole;
+    }
+
+    let format = options.format;
+    let paths: Vec<(usize, usize, String)> = {
+        let sizes: Vec<u64> =
+            pages.iter().map(|p| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0)).collect();
+        let volumes = utils::archive::plan_volumes(&sizes, DOC_LIMIT_SIZE - VOLUME_MARGIN);
+        let count = volumes.len();
+        volumes
+            .into_iter()
+            .enumerate()
+            .map(|(i, range)| {
+                (range.start, range.end, volume_path(config, &detail.title, options, i + 1, count))
+            })
+            .collect()
+    };
+    if paths.len() < 2 {
+        return whole;
+    }
+
+    let reusable = tokio::task::spawn_blocking({
+        let archive = archive.to_string();
+        let paths = paths.clone();
+        move || {
+            let built = std::fs::metadata(&archive).and_then(|m| m.modified()).ok();
+            paths.iter().all(|(start, end, path)| {
+                let fresh = std::fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|t| built.is_some_and(|b| t >= b));
+                fresh && utils::archive::verify(format, path, end - start)
+            })
+        }
+    })
+    .await
+    .unwrap_or(false);
+    if reusable {
+        info!(archive, volumes = paths.len(), "复用已拆分的分卷");
+        return paths.into_iter().map(|(_, _, path)| path).collect();
+    }
+
+    let dir = format!("{}/{}", config.server.download_path, detail.title);
+    let detail = detail.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::with_capacity(paths.len());
+        for (start, end, path) in paths {
+            let src = ArchiveSource {
+                dir: &dir,
+                pages: &pages[start..end],
+                detail: &detail,
+                source_url: &source_url,
+                cover: cover.as_deref(),
+            };
+            utils::archive::build(format, &src, &path)?;
+            files.push(path);
+        }
+        Ok::<_, BotError>(files)
+    })
+    .await
+    .map_err(|e| BotError::InternalError(e.to_string()))
+    .and_then(|r| r);
+
+    match result {
+        Ok(files) => {
+            info!(archive, volumes = files.len(), "压缩包超过文档上限，已拆分为分卷");
+            files
+        }
+        Err(e) => {
+            error!(archive, error = %e, "分卷打包失败，改为发送下载链接");
+            whole
+        }
+    }
+}
+
+async fn download_task(
+    bot: &Bot,
+    job: &Job,
+    detail: &MangaDetail,
     images: Vec<String>,
     config: &crate::config::Config,
 ) -> Result<()> {
+    let title = &job.title;
+
     let manga_dir = format!("{}/{}", config.server.download_path, title);
-    if tokio::fs::metadata(&manga_dir).await.is_err() {
-        tokio::fs::create_dir_all(&manga_dir).await?;
+    jobs::store().update(&job.id, |j| j.status = JobStatus::Downloading).await?;
+    let (progress_tx, progress_rx) = watch::channel(DownloadProgress::default());
+    let reporter = tokio::spawn(report_progress(bot.clone(), job.clone(), progress_rx));
+    let report = download_work(job, &manga_dir, images, config, Some(&progress_tx)).await;
+    reporter.abort();
+    let report = report?;
+
+    let done = report.succeeded();
+    let total = report.total();
+    let missing = missing_text(&report);
+    if done == 0 || (!report.is_complete() && !config.server.allow_incomplete) {
+        // 不打包残缺的作品，列出缺失页码并提供重试
+        let text = format!(
+            "【{}】\n\n{}",
+            utils::escape_md_v2(title),
+            utils::escape_md_v2(&format!(
+                "❌ 下载不完整，已放弃打包: {}/{} 页\n缺失页码: {}",
+                done, total, missing
+            ))
+        );
+        for sub in current_subscribers(job).await {
+            bot.send_message(ChatId(sub.chat_id), text.clone())
+                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                .reply_markup(retry_markup(job.aid, &job.options).await?)
+                .await?;
+            bot.delete_message(ChatId(sub.chat_id), MessageId(sub.status_msg_id)).await?;
+        }
+        return Ok(());
     }
 
-    utils::http::download_batch(images, &manga_dir, config.server.download_concurrency).await;
+    let pages: Vec<String> =
+        report.pages.iter().filter(|p| p.is_ok()).map(|p| p.file_path.clone()).collect();
+    let pages = if job.options.transcode == Transcode::Original {
+        pages
+    } else {
+        jobs::store()
+            .update(&job.id, |j| {
+                j.done = done;
+                j.status = JobStatus::Transcoding;
+            })
+            .await?;
+        transcode_pages(pages, job.options.transcode, config.server.jpeg_quality).await?
+    };
+
+    jobs::store()
+        .update(&job.id, |j| {
+            j.done = done;
+            j.status = JobStatus::Archiving;
+        })
+        .await?;
 
-    let zip_path = format!("{}/{}.zip", config.server.download_path, title);
+    let zip_path = archive_path(config, title, &job.options);
+    let source_url = super::info::build_info_url(&config.manga.base_url, &job.aid.to_string());
+    // EPUB 需要单独的封面图，拉取失败或校验不通过时退回第一页
+    let cover = match job.options.format {
+        ArchiveFormat::Epub if !detail.cover.is_empty() => {
+            let url = utils::http::resolve_url(&detail.cover, &config.manga.base_url);
+            utils::http::fetch_bytes(&url)
+                .await
+                .and_then(|bytes| utils::img::verify_image(&bytes).map(|_| bytes))
+                .inspect_err(|e| error!(aid = job.aid, error = %e, "封面下载失败"))
+                .ok()
+        }
+        _ => None,
+    };
     tokio::task::spawn_blocking({
         let manga_dir = manga_dir.clone();
         let zip_path = zip_path.clone();
-        move || utils::zip::compress_dir(&manga_dir, &zip_path)
+        let format = job.options.format;
+        let detail = detail.clone();
+        let pages = pages.clone();
+        let source_url = source_url.clone();
+        let cover = cover.clone();
+        move || {
+            let src = ArchiveSource {
+                dir: &manga_dir,
+                pages: &pages,
+                detail: &detail,
+                source_url: &source_url,
+                cover: cover.as_deref(),
+            };
+            utils::archive::build(format, &src, &zip_path)
+        }
     })
     .await
     .map_err(|e| crate::error::BotError::InternalError(e.to_string()))??;
 
-    if let Ok(zip_meta) = tokio::fs::metadata(&zip_path).await {
-        if zip_meta.len() < DOC_LIMIT_SIZE {
-            bot.send_document(chat_id, InputFile::file(&zip_path)).await?;
-        } else {
-            let token = uuid::Uuid::new_v4().to_string();
-            utils::cache::download_token_cache().insert(token.clone(), zip_path.clone()).await;
+    let incomplete_note = (!report.is_complete())
+        .then(|| format!("⚠️ 不完整: {}/{} 页\n缺失页码: {}", done, total, missing));
 
-            let host = if config.server.web_host.ends_with('/') {
-                &config.server.web_host[..config.server.web_host.len() - 1]
-            } else {
-                &config.server.web_host
-            };
-            let download_url = format!("{}/download?token={}", host, token);
-            let msg =
-                format!("[点击下载⬇️ {}]({})", utils::escape_md_v2(&title), download_url);
+    let files =
+        split_if_oversize(config, &job.options, &zip_path, detail, pages, source_url, cover).await;
+
+    jobs::store().update(&job.id, |j| j.status = JobStatus::Sending).await?;
+    let subscribers = current_subscribers(job).await;
+    let chat_ids: Vec<ChatId> = subscribers.iter().map(|s| ChatId(s.chat_id)).collect();
+    let retry = retry_markup(job.aid, &job.options).await?;
+    deliver_archive(bot, config, &chat_ids, &files, title, incomplete_note.as_deref(), retry)
+        .await?;
+
+    // 删除临时提示消息
+    for sub in subscribers {
+        bot.delete_message(ChatId(sub.chat_id), MessageId(sub.status_msg_id)).await?;
+    }
+
+    Ok(())
+}
+
+/// 下载作品中任务选中的页面并按配置重试失败页，写入页面清单
+async fn download_work(
+    job: &Job,
+    manga_dir: &str,
+    images: Vec<String>,
+    config: &crate::config::Config,
+    progress: Option<&watch::Sender<DownloadProgress>>,
+) -> Result<DownloadReport> {
+    if tokio::fs::metadata(manga_dir).await.is_err() {
+        tokio::fs::create_dir_all(manga_dir).await?;
+    }
+
+    let total = images.len();
+    let concurrency = config.server.download_concurrency;
+    let selected = select_images(images, &job.options);
+    let mut report =
+        utils::http::download_pages(selected, total, manga_dir, concurrency, progress).await;
+
+    for _ in 0..config.server.download_retry_rounds {
+        if report.is_complete() {
+            break;
+        }
+        utils::http::retry_failed(&mut report, total, manga_dir, concurrency).await;
+    }
+
+    if let Err(e) = utils::http::write_manifest(manga_dir, &report).await {
+        error!(job = %job.id, error = %e, "写入页面清单失败");
+    }
+
+    for page in report.failed() {
+        error!(
+            job = %job.id,
+            page = page.index + 1,
+            url = %page.url,
+            attempts = page.attempts,
+            error = page.error.as_deref().unwrap_or_default(),
+            "页面下载失败"
+        );
+    }
+    Ok(report)
+}
+
+/// 缺失页码说明，损坏的页面单独列出
+fn missing_text(report: &DownloadReport) -> String {
+    let mut missing = utils::pages::format_ranges(&report.missing_pages());
+    let corrupted = report.corrupted_pages();
+    if !corrupted.is_empty() {
+        missing.push_str(&format!("\n其中图片损坏: {}", utils::pages::format_ranges(&corrupted)));
+    }
+    missing
+}
 
-            bot.send_message(chat_id, msg)
+/// 合并打包：逐部下载到各自的页面目录，再打成一个每部作品一个目录的 zip；
+/// 下载失败（或不允许不完整时残缺）的作品跳过并在结果中说明
+async fn combined_task(
+    bot: &Bot,
+    job: &Job,
+    works: &[(MangaDetail, Vec<String>)],
+    config: &crate::config::Config,
+) -> Result<()> {
+    jobs::store().update(&job.id, |j| j.status = JobStatus::Downloading).await?;
+    let (progress_tx, progress_rx) = watch::channel(DownloadProgress::default());
+    let reporter = tokio::spawn(report_progress(bot.clone(), job.clone(), progress_rx));
+
+    let mut overall = DownloadProgress { total: job.total, ..Default::default() };
+    let mut folders: Vec<(String, Vec<String>)> = Vec::with_capacity(works.len());
+    let mut notes = Vec::new();
+    for (detail, images) in works {
+        // 单部作品的进度叠加到整批进度上
+        let (tx, mut rx) = watch::channel(DownloadProgress::default());
+        let forward = tokio::spawn({
+            let progress_tx = progress_tx.clone();
+            let base = overall.clone();
+            async move {
+                while rx.changed().await.is_ok() {
+                    let p = rx.borrow_and_update().clone();
+                    progress_tx.send_replace(DownloadProgress {
+                        total: base.total,
+                        done: base.done + p.done,
+                        failed: base.failed + p.failed,
+                        bytes: base.bytes + p.bytes,
+                    });
+                }
+            }
+        });
+        let manga_dir = format!("{}/{}", config.server.download_path, detail.title);
+        let report = download_work(job, &manga_dir, images.clone(), config, Some(&tx)).await;
+        drop(tx);
+        let _ = forward.await;
+        let report = report?;
+
+        let done = report.succeeded();
+        overall.done += done;
+        overall.failed += report.total() - done;
+        overall.bytes += report.pages.iter().filter(|p| p.is_ok()).map(|p| p.bytes).sum::<u64>();
+        progress_tx.send_replace(overall.clone());
+
+        if done == 0 || (!report.is_complete() && !config.server.allow_incomplete) {
+            notes.push(format!(
+                "❌ 【{}】下载不完整，已跳过: {}/{} 页",
+                detail.title,
+                done,
+                report.total()
+            ));
+            continue;
+        }
+        if !report.is_complete() {
+            notes.push(format!(
+                "⚠️ 【{}】不完整: {}/{} 页\n缺失页码: {}",
+                detail.title,
+                done,
+                report.total(),
+                missing_text(&report)
+            ));
+        }
+        let pages = report.pages.iter().filter(|p| p.is_ok()).map(|p| p.file_path.clone());
+        folders.push((detail.title.clone(), pages.collect()));
+    }
+    reporter.abort();
+
+    let note = (!notes.is_empty()).then(|| notes.join("\n\n"));
+    let retry = combined_retry_markup(job).await?;
+    if folders.is_empty() {
+        let text = format!(
+            "【{}】\n\n{}",
+            utils::escape_md_v2(&job.title),
+            utils::escape_md_v2(note.as_deref().unwrap_or_default())
+        );
+        for sub in current_subscribers(job).await {
+            bot.send_message(ChatId(sub.chat_id), text.clone())
                 .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                .reply_markup(retry.clone())
                 .await?;
+            bot.delete_message(ChatId(sub.chat_id), MessageId(sub.status_msg_id)).await?;
         }
+        return Ok(());
     }
 
-    // 删除临时提示消息
-    bot.delete_message(chat_id, reply_msg_id).await?;
+    if job.options.transcode != Transcode::Original {
+        jobs::store().update(&job.id, |j| j.status = JobStatus::Transcoding).await?;
+        for (_, pages) in folders.iter_mut() {
+            *pages = transcode_pages(
+                std::mem::take(pages),
+                job.options.transcode,
+                config.server.jpeg_quality,
+            )
+            .await?;
+        }
+    }
+
+    jobs::store().update(&job.id, |j| j.status = JobStatus::Archiving).await?;
+    let zip_path = archive_path(config, &job.title, &job.options);
+    tokio::task::spawn_blocking({
+        let zip_path = zip_path.clone();
+        move || utils::zip::compress_folders(&folders, &zip_path)
+    })
+    .await
+    .map_err(|e| BotError::InternalError(e.to_string()))??;
+
+    jobs::store().update(&job.id, |j| j.status = JobStatus::Sending).await?;
+    let subscribers = current_subscribers(job).await;
+    let chat_ids: Vec<ChatId> = subscribers.iter().map(|s| ChatId(s.chat_id)).collect();
+    let files = [zip_path];
+    deliver_archive(bot, config, &chat_ids, &files, &job.title, note.as_deref(), retry).await?;
+
+    for sub in subscribers {
+        bot.delete_message(ChatId(sub.chat_id), MessageId(sub.status_msg_id)).await?;
+    }
 
     Ok(())
 }
+
+/// 逐页转码（阻塞操作放到 spawn_blocking），单页失败时保留原图
+async fn transcode_pages(pages: Vec<String>, mode: Transcode, quality: u8) -> Result<Vec<String>> {
+    tokio::task::spawn_blocking(move || {
+        pages
+            .into_iter()
+            .map(|page| {
+                utils::img::transcode(&page, mode, quality).unwrap_or_else(|e| {
+                    error!(page = %page, error = %e, "页面转码失败，保留原图");
+                    page
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| BotError::InternalError(e.to_string()))
+}
+
+async fn current_subscribers(job: &Job) -> Vec<Subscriber> {
+    jobs::store().get(&job.id).await.unwrap_or_else(|| job.clone()).subscribers()
+}
+
+/// 把打包文件按顺序推送给所有会话：小于文档上限的直接发送（首次上传后复用 file_id），
+/// 超出上限的合并成一条下载链接消息；不完整提示附在最后一条消息上
+async fn deliver_archive(
+    bot: &Bot,
+    config: &crate::config::Config,
+    chat_ids: &[ChatId],
+    files: &[String],
+    title: &str,
+    incomplete_note: Option<&str>,
+    retry: InlineKeyboardMarkup,
+) -> Result<()> {
+    let mut documents = Vec::with_capacity(files.len());
+    let mut links = Vec::new();
+    for path in files {
+        if tokio::fs::metadata(path).await?.len() < DOC_LIMIT_SIZE {
+            documents.push(path);
+        } else {
+            links.push(path);
+        }
+    }
+    // 分卷模式下只在配置允许时退回下载链接，此时一个文件都不发送
+    if !links.is_empty() && config.server.split_volumes && !config.server.link_fallback {
+        let name = std::path::Path::new(links[0]).file_name().unwrap_or_default();
+        return Err(BotError::InternalError(format!(
+            "{} exceeds the document size limit and link fallback is disabled",
+            name.to_string_lossy()
+        )));
+    }
+
+    for (i, path) in documents.iter().enumerate() {
+        let note = incomplete_note.filter(|_| links.is_empty() && i + 1 == documents.len());
+        let mut file_id: Option<String> = None;
+        for chat_id in chat_ids {
+            let file = match &file_id {
+                Some(id) => InputFile::file_id(id.clone()),
+                None => InputFile::file(path.as_str()),
+            };
+            let mut req = bot.send_document(*chat_id, file);
+            if let Some(note) = note {
+                req = req.caption(note).reply_markup(retry.clone());
+            }
+            let sent = req.await?;
+            if file_id.is_none() {
+                file_id = sent.document().map(|d| d.file.id.clone());
+            }
+        }
+    }
+
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    let mut lines = Vec::with_capacity(links.len());
+    for path in links {
+        let Some(link) = services::links::sign_file(config, path).await else {
+            error!(path = %path, "文件不在下载目录内，无法生成下载链接");
+            continue;
+        };
+        let download_url = services::links::download_url(config, &link);
+        // 单个文件沿用作品标题，分卷时显示各自的文件名
+        let name = if files.len() == 1 {
+            title
+        } else {
+            std::path::Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or(title)
+        };
+        lines.push(format!("[点击下载⬇️ {}]({})", utils::escape_md_v2(name), download_url));
+    }
+    let mut msg = lines.join("\n");
+    msg.push_str(&format!("\n\n{}", utils::escape_md_v2(&link_note(config))));
+
+    if let Some(note) = incomplete_note {
+        msg.push_str(&format!("\n\n{}", utils::escape_md_v2(note)));
+    }
+
+    for chat_id in chat_ids {
+        let mut req = bot
+            .send_message(*chat_id, msg.clone())
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2);
+        if incomplete_note.is_some() {
+            req = req.reply_markup(retry.clone());
+        }
+        req.await?;
+    }
+
+    Ok(())
+}
+
+/// 下载链接的有效期与次数说明
+fn link_note(config: &crate::config::Config) -> String {
+    let mut note = format!("🔗 链接 {} 小时内有效", config.server.link_ttl_hours);
+    match config.server.link_max_downloads {
+        0 => {}
+        1 => note.push_str("，仅可下载一次"),
+        n => note.push_str(&format!("，最多下载 {} 次", n)),
+    }
+    note
+}
+
+/// 定期把下载进度编辑到提示消息上（Telegram 对编辑频率有限制，因此合并中间状态）
+async fn report_progress(bot: Bot, job: Job, mut rx: watch::Receiver<DownloadProgress>) {
+    let started = Instant::now();
+    while rx.changed().await.is_ok() {
+        let progress = rx.borrow_and_update().clone();
+
+        let text = format!(
+            "【{}】\n\n{}",
+            utils::escape_md_v2(&job.title),
+            utils::escape_md_v2(&progress_text(&progress, started.elapsed()))
+        );
+        for (i, sub) in current_subscribers(&job).await.into_iter().enumerate() {
+            // 只有发起者的消息带取消按钮
+            let mut req = bot
+                .edit_message_text(ChatId(sub.chat_id), MessageId(sub.status_msg_id), text.clone())
+                .parse_mode(teloxide::types::ParseMode::MarkdownV2);
+            if i == 0 {
+                req = req.reply_markup(cancel_markup(&job.id).unwrap_or_default());
+            }
+            if let Err(e) = req.await {
+                debug!(job = %job.id, error = %e, "进度消息更新失败");
+            }
+        }
+
+        let done = progress.done;
+        let _ = jobs::store().update(&job.id, |j| j.done = done).await;
+
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+    }
+}
+
+fn progress_text(p: &DownloadProgress, elapsed: Duration) -> String {
+    let finished = p.done + p.failed;
+    let percent = (finished * 100).checked_div(p.total).unwrap_or(0);
+
+    let filled = percent * PROGRESS_BAR_WIDTH / 100;
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(PROGRESS_BAR_WIDTH - filled));
+
+    let eta = if finished == 0 || finished >= p.total {
+        "--".to_string()
+    } else {
+        let remaining = elapsed.as_secs_f64() / finished as f64 * (p.total - finished) as f64;
+        utils::human_duration(Duration::from_secs_f64(remaining))
+    };
+
+    format!(
+        "⬇️ 下载中 [{}] {}%\n📄 {}/{}  ❌ {}  📦 {}\n⏱ 预计剩余 {}",
+        bar,
+        percent,
+        p.done,
+        p.total,
+        p.failed,
+        utils::human_bytes(p.bytes),
+        eta
+    )
+}
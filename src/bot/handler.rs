@@ -1,4 +1,4 @@
-use crate::bot::commands::{Command, cate, info, preview, rank, search, start, zip, menu};
+use crate::bot::commands::{Command, cate, info, limit, preview, rank, search, start, zip, menu};
 use crate::error::Result;
 use crate::utils;
 use std::sync::Arc;
@@ -10,6 +10,7 @@ use crate::bot::commands::menu::MenuType;
 async fn dispatch_command(
     bot: Bot,
     msg: Message,
+    user: UserId,
     cmd: Command,
     config: &Arc<crate::config::Config>,
 ) -> Result<bool> {
@@ -22,6 +23,8 @@ async fn dispatch_command(
         _ => false,
     };
 
+    let name = cmd.name();
+    let started = std::time::Instant::now();
     let result = match cmd {
         Command::Start(_payload) => start::handle(&bot, &msg).await,
         Command::Search(key, typ, page) => {
@@ -30,7 +33,11 @@ async fn dispatch_command(
         Command::Rank(period, page) => rank::handle(&bot, &msg, &config, period, page).await,
         Command::Info(aid) => info::handle(&bot, &msg, &config, aid).await,
         Command::Preview(aid, page) => preview::handle(&bot, &msg, &config, aid, page).await,
-        Command::Zip(aid) => zip::handle(&bot, &msg, &config, aid).await,
+        Command::Zip(args) => zip::handle(&bot, &msg, user, &config, args).await,
+        Command::Stream(aid) => zip::stream(&bot, &msg, config, aid).await,
+        Command::Jobs => zip::list_jobs(&bot, &msg).await,
+        Command::Cancel(job_id) => zip::cancel(&bot, &msg, job_id).await,
+        Command::Limit(global, job) => limit::handle(&bot, &msg, global, job).await,
         Command::Cate(cate, sub, page) => cate::handle(&bot, &msg, &config, cate, sub, page).await,
         Command::Menu_Rank => menu::handle(&bot, &msg, MenuType::Rank).await,
         Command::Menu_Cate_TRZ => menu::handle(&bot, &msg, MenuType::CateTrz).await,
@@ -38,6 +45,7 @@ async fn dispatch_command(
         Command::Menu_Cate_DP => menu::handle(&bot, &msg, MenuType::CateDp).await,
         Command::Menu_Cate_HM => menu::handle(&bot, &msg, MenuType::CateHm).await,
     };
+    utils::metrics::record_command(name, result.is_ok(), started.elapsed());
 
     if let Err(ref e) = result {
         error!("error: {:?}", e);
@@ -85,7 +93,8 @@ pub async fn handle_command(
         return Ok(());
     }
 
-    dispatch_command(bot, msg, cmd, &config).await?;
+    let user = msg.from.as_ref().unwrap().id;
+    dispatch_command(bot, msg, user, cmd, &config).await?;
     Ok(())
 }
 
@@ -120,7 +129,7 @@ pub async fn handle_callback(
     bot.answer_callback_query(cq.id.clone()).text("⏳ 处理中...").show_alert(false).await?;
 
     if let Some(msg) = cq.regular_message() {
-        if dispatch_command(bot.clone(), msg.clone(), cmd, &config).await? {
+        if dispatch_command(bot.clone(), msg.clone(), cq.from.id, cmd, &config).await? {
             bot.delete_message(msg.chat.id, msg.id).await?;
         }
     }
@@ -1,4 +1,6 @@
 use crate::bot::commands::Command;
+use crate::services::webhook::{self, UpdateMode};
+use crate::services::{health, shutdown};
 use std::sync::Arc;
 use teloxide::dispatching::HandlerExt;
 use teloxide::dispatching::{Dispatcher, UpdateFilterExt};
@@ -9,6 +11,15 @@ use teloxide::{Bot, dptree};
 pub mod commands;
 pub mod handler;
 
+/// 在后台恢复未完成的下载任务，避免逐个获取作品信息时阻塞接收更新
+fn spawn_resume(bot: &Bot, config: &crate::config::Config) {
+    let bot = bot.clone();
+    let config = config.clone();
+    tokio::spawn(async move {
+        commands::zip::resume_jobs(&bot, &config).await;
+    });
+}
+
 pub async fn run(bot: Bot, config: crate::config::Config) -> crate::error::Result<()> {
     let config = Arc::new(config);
     let handler =
@@ -29,13 +40,44 @@ pub async fn run(bot: Bot, config: crate::config::Config) -> crate::error::Resul
                 },
             ));
 
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![config])
+    let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
+        .dependencies(dptree::deps![config.clone()])
         .error_handler(LoggingErrorHandler::with_custom_text("Bot运行时错误"))
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .build();
+
+    // 收到关闭信号后停止接收更新，等待正在处理的更新完成
+    let dispatcher_shutdown = dispatcher.shutdown_token();
+    tokio::spawn(async move {
+        shutdown::token().cancelled().await;
+        loop {
+            match dispatcher_shutdown.shutdown() {
+                Ok(stopped) => break stopped.await,
+                // 分发循环尚未启动，稍后重试
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+            }
+        }
+    });
+
+    match config.bot.update_mode {
+        UpdateMode::Polling => {
+            health::set_dispatcher_running(true);
+            spawn_resume(&bot, &config);
+            dispatcher.dispatch().await;
+        }
+        UpdateMode::Webhook => {
+            let listener = webhook::listener()?;
+            webhook::register(&bot, &config).await?;
+            health::set_dispatcher_running(true);
+            spawn_resume(&bot, &config);
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("webhook 更新错误"),
+                )
+                .await;
+        }
+    }
+    health::set_dispatcher_running(false);
 
     Ok(())
 }
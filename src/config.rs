@@ -1,3 +1,6 @@
+use crate::services::webhook::UpdateMode;
+use crate::utils::archive::ArchiveFormat;
+use crate::utils::img::Transcode;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -12,6 +15,9 @@ pub struct BotConfig {
     pub bot_name: String,
     pub telegram_token: String,
     pub admin_ids: Vec<u64>,
+    pub update_mode: UpdateMode,
+    pub webhook_url: String,
+    pub webhook_secret: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -20,14 +26,41 @@ pub struct ServerConfig {
     pub web_host: String,
     pub http_timeout: u64,
     pub download_timeout: u64,
+    pub shutdown_timeout: u64,
     pub log_level: String,
     pub log_path: String,
     pub download_path: String,
     pub download_concurrency: usize,
-    pub cache_download_token_minute_ttl: u64,
-    pub cache_download_token_max_size: u64,
+    pub max_concurrent_jobs: usize,
+    pub max_concurrent_fetches: usize,
+    pub bandwidth_limit: u64,
+    pub job_bandwidth_limit: u64,
+    pub download_retry_rounds: u32,
+    pub allow_incomplete: bool,
+    pub archive_format: ArchiveFormat,
+    pub split_volumes: bool,
+    pub link_fallback: bool,
+    pub transcode: Transcode,
+    pub jpeg_quality: u8,
+    pub retention_max_mb: u64,
+    pub retention_max_age_hours: u64,
+    pub retention_keep_recent: usize,
+    pub retention_interval_minute: u64,
+    pub link_secret: String,
+    pub link_ttl_hours: u64,
+    pub link_max_downloads: u32,
+    pub reader_ttl_hours: u64,
+    pub image_cache_path: String,
+    pub image_cache_max_mb: u64,
+    pub proxy_allowed_hosts: Vec<String>,
+    pub api_keys: Vec<String>,
+    pub health_parse_max_age_minute: u64,
+    pub cache_stream_token_minute_ttl: u64,
+    pub cache_stream_token_max_size: u64,
     pub cache_search_key_num_minute_ttl: u64,
     pub cache_search_key_num_max_size: u64,
+    pub cache_button_args_minute_ttl: u64,
+    pub cache_button_args_max_size: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -48,18 +81,48 @@ impl Config {
             .set_default("bot.bot_name", "mangars_bot")?
             .set_default("bot.telegram_token", "")?
             .set_default("bot.admin_ids", Vec::<i64>::new())?
+            .set_default("bot.update_mode", "polling")?
+            .set_default("bot.webhook_url", "")?
+            .set_default("bot.webhook_secret", "")?
             .set_default("server.port", 8087)?
             .set_default("server.web_host", "http://localhost:8087")?
             .set_default("server.http_timeout", 10)?
             .set_default("server.download_timeout", 15)?
+            .set_default("server.shutdown_timeout", 30)?
             .set_default("server.log_level", "info")?
             .set_default("server.log_path", "/tmp/mangabot/app.log")?
             .set_default("server.download_path", "/tmp/mangabot/downloads")?
             .set_default("server.download_concurrency", 5)?
-            .set_default("server.cache_download_token_minute_ttl", 10)?
-            .set_default("server.cache_download_token_max_size", 256)?
+            .set_default("server.max_concurrent_jobs", 2)?
+            .set_default("server.max_concurrent_fetches", 10)?
+            .set_default("server.bandwidth_limit", 0)?
+            .set_default("server.job_bandwidth_limit", 0)?
+            .set_default("server.download_retry_rounds", 1)?
+            .set_default("server.allow_incomplete", true)?
+            .set_default("server.archive_format", "zip")?
+            .set_default("server.split_volumes", true)?
+            .set_default("server.link_fallback", true)?
+            .set_default("server.transcode", "original")?
+            .set_default("server.jpeg_quality", 85)?
+            .set_default("server.retention_max_mb", 0)?
+            .set_default("server.retention_max_age_hours", 0)?
+            .set_default("server.retention_keep_recent", 0)?
+            .set_default("server.retention_interval_minute", 60)?
+            .set_default("server.link_secret", "")?
+            .set_default("server.link_ttl_hours", 72)?
+            .set_default("server.link_max_downloads", 0)?
+            .set_default("server.reader_ttl_hours", 24)?
+            .set_default("server.image_cache_path", "/tmp/mangabot/images")?
+            .set_default("server.image_cache_max_mb", 512)?
+            .set_default("server.proxy_allowed_hosts", Vec::<String>::new())?
+            .set_default("server.api_keys", Vec::<String>::new())?
+            .set_default("server.health_parse_max_age_minute", 60)?
+            .set_default("server.cache_stream_token_minute_ttl", 10)?
+            .set_default("server.cache_stream_token_max_size", 256)?
             .set_default("server.cache_search_key_num_minute_ttl", 30)?
             .set_default("server.cache_search_key_num_max_size", 1000000)?
+            .set_default("server.cache_button_args_minute_ttl", 60)?
+            .set_default("server.cache_button_args_max_size", 10000)?
             .set_default("manga.base_url", "")?
             .set_default("manga.preview_size", 10)?
             .set_default("manga.cache_image_minute_ttl", 20)?
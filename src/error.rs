@@ -35,9 +35,21 @@ pub enum BotError {
     #[error("压缩错误: {0}")]
     Zip(#[from] zip::result::ZipError),
 
+    #[error("序列化错误: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("遍历错误: {0}")]
     Walkdir(#[from] walkdir::Error),
 
+    #[error("图片校验失败: {0}")]
+    InvalidImage(String),
+
+    #[error("任务已取消")]
+    Cancelled,
+
+    #[error("服务正在关闭")]
+    ShuttingDown,
+
     #[error("内部错误: {0}")]
     InternalError(String),
 }
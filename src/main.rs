@@ -1,5 +1,6 @@
 #![forbid(unsafe_code)]
 use crate::config::Config;
+use std::time::Duration;
 use teloxide::Bot;
 use tracing::info;
 
@@ -23,16 +24,27 @@ async fn main() -> crate::error::Result<()> {
     utils::cache::init(&config)?;
     info!("缓存初始化完成");
 
-    {
-        let config_clone = config.clone();
-        if let Err(e) = services::web::start(config_clone) {
-            tracing::error!(error = %e, "web server failed");
-        }
-    }
+    services::jobs::init(&config)?;
+    info!("任务队列初始化完成");
+    services::scheduler::init(&config)?;
+    services::links::init(&config)?;
+    services::proxy::init(&config)?;
+
+    services::retention::spawn(config.clone());
+
+    let server = services::web::start(config.clone())?;
+    services::shutdown::listen_signals();
 
     let bot = Bot::new(&config.bot.telegram_token);
     info!("🚀 Bot启动中...");
-    bot::run(bot, config).await?;
+    let timeout = Duration::from_secs(config.server.shutdown_timeout);
+    let result = bot::run(bot, config).await;
+
+    // 分发循环退出（收到信号或出错）后停止 web 服务，等待下载任务收尾
+    services::shutdown::trigger();
+    let (_, finished) = tokio::join!(server.stop(true), services::shutdown::drain(timeout));
+    info!(finished, "服务已关闭");
+    telemetry::flush();
 
-    Ok(())
+    result
 }
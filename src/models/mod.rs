@@ -1,4 +1,6 @@
-#[derive(Debug, Clone)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct MangaInfo {
     pub id: i64,
     pub rank: i32,
@@ -10,9 +12,8 @@ pub struct MangaInfo {
     pub published: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MangaDetail {
-    #[allow(dead_code)]
     pub id: i64,
     pub title: String,
     pub cover: String,
@@ -22,3 +23,10 @@ pub struct MangaDetail {
     pub tags: Vec<String>,
     pub description: String,
 }
+
+/// 在线打包下载的目标作品：页面按阅读顺序抓取，边下载边输出
+#[derive(Debug, Clone)]
+pub struct ArchiveStream {
+    pub title: String,
+    pub images: Vec<String>,
+}
@@ -0,0 +1,186 @@
+use crate::bot::commands::cate::{Category, build_cate_url};
+use crate::bot::commands::rank::{RankType, build_ranking_url};
+use crate::bot::commands::search::build_search_url;
+use crate::bot::commands::{build_images_url, info::build_info_url};
+use crate::config::Config;
+use crate::error::BotError;
+use crate::services::{jobs, manga};
+use crate::utils::constant_time_eq;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::{HttpRequest, HttpResponse, web};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::error;
+
+/// 列表接口的通用参数，与对应的机器人命令一致
+#[derive(Deserialize)]
+pub struct ListQuery {
+    /// 排行榜周期 day/week/month
+    period: Option<String>,
+    /// 分类与子分类，如 trz/zh
+    cate: Option<String>,
+    sub: Option<String>,
+    /// 搜索关键字与类型 a（全部）/u（作者）/t（标签）
+    q: Option<String>,
+    #[serde(rename = "type")]
+    typ: Option<String>,
+    page: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct Images {
+    aid: i64,
+    total: usize,
+    images: Vec<String>,
+}
+
+/// 校验 API 密钥：`Authorization: Bearer <key>`、`X-Api-Key: <key>`，
+/// 或 HTTP Basic 认证的密码（供 OPDS 阅读器使用）；未配置任何密钥时接口不可用。
+/// 校验失败时返回应答（404 或 401），JSON 接口与 OPDS 目录共用
+pub(crate) fn authorize(req: &HttpRequest, config: &Config) -> Option<HttpResponse> {
+    if config.server.api_keys.is_empty() {
+        return Some(HttpResponse::NotFound().finish());
+    }
+    if is_authorized(req, config) {
+        return None;
+    }
+    Some(HttpResponse::Unauthorized().json(json!({ "error": "invalid api key" })))
+}
+
+fn is_authorized(req: &HttpRequest, config: &Config) -> bool {
+    let headers = req.headers();
+    let authorization = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+    let key = authorization
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| authorization.and_then(|v| v.strip_prefix("Basic ")).and_then(basic_password))
+        .or_else(|| headers.get("X-Api-Key").and_then(|v| v.to_str().ok()).map(str::to_string));
+    key.is_some_and(|key| {
+        let key = key.trim().as_bytes();
+        // 逐个比较全部密钥，耗时不随匹配位置变化
+        config.server.api_keys.iter().fold(false, |ok, k| constant_time_eq(k.as_bytes(), key) | ok)
+    })
+}
+
+/// Basic 认证中 `user:password` 的密码部分，用户名任意
+fn basic_password(encoded: &str) -> Option<String> {
+    let decoded = STANDARD.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    decoded.split_once(':').map(|(_, password)| password.to_string())
+}
+
+fn upstream_error(e: BotError) -> HttpResponse {
+    error!(error = %e, "api request failed");
+    HttpResponse::BadGateway().json(json!({ "error": e.to_string() }))
+}
+
+async fn rank(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    query: web::Query<ListQuery>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let period = query.period.as_deref().unwrap_or("day");
+    let rank_type = RankType::from_str(period).unwrap_or(RankType::Day);
+    let page = query.page.unwrap_or(1).clamp(1, 1000);
+    let url = build_ranking_url(&config.manga.base_url, rank_type, page);
+    match manga::parse_rank(&url, &config.manga.base_url).await {
+        Ok(mangas) => HttpResponse::Ok().json(mangas),
+        Err(e) => upstream_error(e),
+    }
+}
+
+async fn cate(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    query: web::Query<ListQuery>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let category = Category::from_str(
+        query.cate.as_deref().unwrap_or("同人志"),
+        query.sub.as_deref().unwrap_or("汉化"),
+    );
+    let (_, cate_num) = category.to_cate_info();
+    let page = query.page.unwrap_or(1).clamp(1, 1000);
+    let url = build_cate_url(&config.manga.base_url, cate_num, page);
+    match manga::parse_cate(&url, &config.manga.base_url).await {
+        Ok(mangas) => HttpResponse::Ok().json(mangas),
+        Err(e) => upstream_error(e),
+    }
+}
+
+async fn search(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    query: web::Query<ListQuery>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let Some(key) = query.q.as_deref().filter(|q| !q.trim().is_empty()) else {
+        return HttpResponse::BadRequest().json(json!({ "error": "missing q" }));
+    };
+    let typ = query.typ.as_deref().unwrap_or("a");
+    let page = query.page.unwrap_or(1).max(1);
+    let url = build_search_url(&config.manga.base_url, key, typ, page);
+    let result = if typ == "t" {
+        manga::parse_cate(&url, &config.manga.base_url).await
+    } else {
+        manga::parse_search(&url, &config.manga.base_url).await
+    };
+    match result {
+        Ok(mangas) => HttpResponse::Ok().json(mangas),
+        Err(e) => upstream_error(e),
+    }
+}
+
+async fn info(req: HttpRequest, config: web::Data<Config>, aid: web::Path<i64>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let aid = aid.into_inner();
+    let base_url = &config.manga.base_url;
+    let url = build_info_url(base_url, &aid.to_string());
+    match manga::parse_detail(aid, &url, base_url).await {
+        Ok(detail) => HttpResponse::Ok().json(detail),
+        Err(e) => upstream_error(e),
+    }
+}
+
+async fn images(req: HttpRequest, config: web::Data<Config>, aid: web::Path<i64>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let aid = aid.into_inner();
+    let base_url = &config.manga.base_url;
+    let sid = aid.to_string();
+    match manga::extract_image_urls(&sid, &build_images_url(base_url, &sid), base_url).await {
+        Ok(images) => HttpResponse::Ok().json(Images { aid, total: images.len(), images }),
+        Err(e) => upstream_error(e),
+    }
+}
+
+/// 所有未完成的下载任务
+async fn list_jobs(req: HttpRequest, config: web::Data<Config>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    HttpResponse::Ok().json(jobs::store().list().await)
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            .route("/rank", web::get().to(rank))
+            .route("/cate", web::get().to(cate))
+            .route("/search", web::get().to(search))
+            .route("/info/{aid}", web::get().to(info))
+            .route("/images/{aid}", web::get().to(images))
+            .route("/jobs", web::get().to(list_jobs)),
+    );
+}
@@ -0,0 +1,155 @@
+use crate::bot::commands::rank::{RankType, build_ranking_url};
+use crate::config::Config;
+use crate::services::manga;
+use crate::utils::cache;
+use actix_web::{HttpRequest, HttpResponse, web};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use tracing::warn;
+
+/// Telegram 分发循环是否在运行
+static DISPATCHER_RUNNING: AtomicBool = AtomicBool::new(false);
+/// 最近一次列表解析返回非空结果的时间（Unix 秒），0 表示从未成功
+static LAST_PARSE_OK: AtomicI64 = AtomicI64::new(0);
+/// 最近一次列表解析的时间与结果数
+static LAST_PARSE_AT: AtomicI64 = AtomicI64::new(0);
+static LAST_PARSE_RESULTS: AtomicUsize = AtomicUsize::new(0);
+/// 最近一次主动探测源站的时间，避免探针频繁请求源站
+static LAST_PROBE: AtomicI64 = AtomicI64::new(0);
+
+/// 两次主动探测的最小间隔（秒）
+const PROBE_INTERVAL_SECS: i64 = 60;
+
+pub fn set_dispatcher_running(running: bool) {
+    DISPATCHER_RUNNING.store(running, Ordering::SeqCst);
+}
+
+pub fn dispatcher_running() -> bool {
+    DISPATCHER_RUNNING.load(Ordering::SeqCst)
+}
+
+/// 记录一次列表页解析的结果数；源站改版时解析结果为空，不会刷新成功时间
+pub fn record_parse(results: usize) {
+    let now = chrono::Utc::now().timestamp();
+    LAST_PARSE_AT.store(now, Ordering::SeqCst);
+    LAST_PARSE_RESULTS.store(results, Ordering::SeqCst);
+    if results > 0 {
+        LAST_PARSE_OK.store(now, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Checks {
+    pub caches: bool,
+    pub download_dir: bool,
+    pub dispatcher: bool,
+    pub upstream: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LastParse {
+    /// 最近一次解析的时间与结果数
+    pub at: Option<i64>,
+    pub results: usize,
+    /// 最近一次解析出结果的时间
+    pub last_success: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: Checks,
+    pub last_parse: LastParse,
+}
+
+/// 汇总各项就绪检查；解析结果过旧时主动请求一次日榜确认源站可用
+pub async fn readiness(config: &Config) -> Readiness {
+    let max_age = config.server.health_parse_max_age_minute as i64 * 60;
+    let mut upstream = parse_fresh(max_age);
+    if !upstream && should_probe() {
+        probe(config).await;
+        upstream = parse_fresh(max_age);
+    }
+
+    let checks = Checks {
+        caches: cache::is_initialized(),
+        download_dir: download_dir_writable(&config.server.download_path).await,
+        dispatcher: dispatcher_running(),
+        upstream,
+    };
+    let ready = checks.caches && checks.download_dir && checks.dispatcher && checks.upstream;
+    let at = LAST_PARSE_AT.load(Ordering::SeqCst);
+    let ok = LAST_PARSE_OK.load(Ordering::SeqCst);
+    Readiness {
+        ready,
+        checks,
+        last_parse: LastParse {
+            at: (at > 0).then_some(at),
+            results: LAST_PARSE_RESULTS.load(Ordering::SeqCst),
+            last_success: (ok > 0).then_some(ok),
+        },
+    }
+}
+
+fn parse_fresh(max_age: i64) -> bool {
+    let ok = LAST_PARSE_OK.load(Ordering::SeqCst);
+    ok > 0 && chrono::Utc::now().timestamp() - ok <= max_age
+}
+
+/// 距上次探测超过间隔时占用本次探测
+fn should_probe() -> bool {
+    let now = chrono::Utc::now().timestamp();
+    let last = LAST_PROBE.load(Ordering::SeqCst);
+    now - last >= PROBE_INTERVAL_SECS
+        && LAST_PROBE.compare_exchange(last, now, Ordering::SeqCst, Ordering::SeqCst).is_ok()
+}
+
+async fn probe(config: &Config) {
+    let base_url = &config.manga.base_url;
+    if base_url.is_empty() {
+        return;
+    }
+    let url = build_ranking_url(base_url, RankType::Day, 1);
+    match manga::parse_rank(&url, base_url).await {
+        Ok(mangas) if mangas.is_empty() => warn!(url, "源站探测未解析到结果"),
+        Ok(_) => {}
+        Err(e) => warn!(url, error = %e, "源站探测失败"),
+    }
+}
+
+/// 在下载目录写入并删除一个探测文件
+async fn download_dir_writable(dir: &str) -> bool {
+    let path = std::path::Path::new(dir).join(".readyz");
+    let result = async {
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(&path, b"ok").await?;
+        tokio::fs::remove_file(&path).await
+    }
+    .await;
+    if let Err(e) = &result {
+        warn!(dir, error = %e, "下载目录不可写");
+    }
+    result.is_ok()
+}
+
+/// 进程存活
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+/// 就绪检查，任一项失败返回 503
+async fn readyz(req: HttpRequest) -> HttpResponse {
+    let Some(config) = req.app_data::<web::Data<Config>>() else {
+        return HttpResponse::InternalServerError().finish();
+    };
+    let readiness = readiness(config).await;
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/healthz", web::get().to(healthz)).route("/readyz", web::get().to(readyz));
+}
@@ -0,0 +1,263 @@
+use crate::config::Config;
+use crate::error::Result;
+use crate::utils::archive::ArchiveFormat;
+use crate::utils::img::Transcode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+static JOB_STORE: OnceLock<JobStore> = OnceLock::new();
+
+static JOURNAL_FILE: &str = ".jobs.json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Downloading,
+    Transcoding,
+    Archiving,
+    Sending,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Downloading => "downloading",
+            Self::Transcoding => "transcoding",
+            Self::Archiving => "archiving",
+            Self::Sending => "sending",
+        }
+    }
+}
+
+/// 等待任务结果的会话及其状态消息
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Subscriber {
+    pub chat_id: i64,
+    pub status_msg_id: i32,
+}
+
+/// 用户为本次下载指定的选项，选项相同的同 aid 请求才会合并
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DownloadOptions {
+    #[serde(default)]
+    pub format: ArchiveFormat,
+    #[serde(default)]
+    pub transcode: Transcode,
+    /// 只下载部分页面时的页码区间（如 "1-20,35"），为空表示整部作品
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pages: Option<String>,
+}
+
+/// 一次 /zip 请求对应的下载任务，落盘保存以便重启后恢复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub aid: i64,
+    pub chat_id: i64,
+    /// 发起请求的用户，调度器按用户轮转；旧版本的任务日志中为 0
+    #[serde(default)]
+    pub user_id: u64,
+    pub status_msg_id: i32,
+    pub title: String,
+    pub status: JobStatus,
+    pub total: usize,
+    pub done: usize,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// 同一 aid 的后续请求合并到本任务，完成后一并推送
+    #[serde(default)]
+    pub followers: Vec<Subscriber>,
+    #[serde(default)]
+    pub options: DownloadOptions,
+    /// 合并打包时包含的全部作品（含 `aid`），单部作品为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub works: Vec<i64>,
+    /// 合并打包时各作品的标题，即页面所在的目录名
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub work_titles: Vec<String>,
+}
+
+/// 入队结果：新建任务，或并入已有的同 aid 任务
+#[derive(Debug, Clone)]
+pub enum Enqueued {
+    Created(Job),
+    Joined(Job),
+}
+
+impl Job {
+    pub fn new(
+        aid: i64,
+        chat_id: i64,
+        status_msg_id: i32,
+        title: String,
+        total: usize,
+        options: DownloadOptions,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            aid,
+            chat_id,
+            user_id: 0,
+            status_msg_id,
+            title,
+            status: JobStatus::Queued,
+            total,
+            done: 0,
+            created_at: now,
+            updated_at: now,
+            followers: Vec::new(),
+            options,
+            works: Vec::new(),
+            work_titles: Vec::new(),
+        }
+    }
+
+    /// 任务写入的作品目录与打包文件名：任务标题，合并打包时另含各作品标题
+    pub fn titles(&self) -> Vec<String> {
+        std::iter::once(self.title.clone()).chain(self.work_titles.iter().cloned()).collect()
+    }
+
+    /// 调度时所属的用户，未记录用户时按会话区分
+    pub fn owner(&self) -> i64 {
+        if self.user_id == 0 { self.chat_id } else { self.user_id as i64 }
+    }
+
+    /// 所有等待结果的会话，发起者在前
+    pub fn subscribers(&self) -> Vec<Subscriber> {
+        let owner = Subscriber { chat_id: self.chat_id, status_msg_id: self.status_msg_id };
+        std::iter::once(owner).chain(self.followers.iter().copied()).collect()
+    }
+}
+
+/// 基于 JSON 日志文件的任务表，每次变更整体重写（先写临时文件再 rename）
+///
+/// 取消令牌只存在于内存中，进程重启后由恢复流程重新登记
+pub struct JobStore {
+    path: PathBuf,
+    jobs: Mutex<BTreeMap<String, Job>>,
+    cancel_tokens: std::sync::Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl JobStore {
+    pub fn open(dir: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = PathBuf::from(dir).join(JOURNAL_FILE);
+
+        let jobs = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<Vec<Job>>(&bytes).unwrap_or_else(|e| {
+                error!(path = %path.display(), error = %e, "任务日志损坏，已忽略");
+                Vec::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let jobs = jobs.into_iter().map(|j| (j.id.clone(), j)).collect();
+        Ok(Self { path, jobs: Mutex::new(jobs), cancel_tokens: Default::default() })
+    }
+
+    /// 同一 aid（合并打包时为同一组作品）、同样选项的任务已存在时把请求者挂到该任务上，否则插入新任务
+    pub async fn insert_or_join(&self, job: Job) -> Result<Enqueued> {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(existing) = jobs
+            .values_mut()
+            .find(|j| j.aid == job.aid && j.options == job.options && j.works == job.works)
+        {
+            existing
+                .followers
+                .push(Subscriber { chat_id: job.chat_id, status_msg_id: job.status_msg_id });
+            let existing = existing.clone();
+            self.persist(&jobs).await?;
+            return Ok(Enqueued::Joined(existing));
+        }
+
+        jobs.insert(job.id.clone(), job.clone());
+        self.persist(&jobs).await?;
+        Ok(Enqueued::Created(job))
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().await.get(id).cloned()
+    }
+
+    pub async fn update<F>(&self, id: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Job),
+    {
+        let mut jobs = self.jobs.lock().await;
+        let Some(job) = jobs.get_mut(id) else {
+            return Ok(());
+        };
+        f(job);
+        job.updated_at = chrono::Utc::now().timestamp();
+        self.persist(&jobs).await
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<()> {
+        self.cancel_tokens.lock().unwrap().remove(id);
+        let mut jobs = self.jobs.lock().await;
+        if jobs.remove(id).is_some() {
+            self.persist(&jobs).await?;
+        }
+        Ok(())
+    }
+
+    /// 所有未完成的任务，按创建时间排序
+    pub async fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs.lock().await.values().cloned().collect();
+        jobs.sort_by_key(|j| j.created_at);
+        jobs
+    }
+
+    /// 获取（必要时创建）任务的取消令牌
+    pub fn cancel_token(&self, id: &str) -> CancellationToken {
+        self.cancel_tokens.lock().unwrap().entry(id.to_string()).or_default().clone()
+    }
+
+    /// 取消正在运行的任务，任务不存在时返回 false
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.cancel_tokens.lock().unwrap().get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn persist(&self, jobs: &BTreeMap<String, Job>) -> Result<()> {
+        let list: Vec<&Job> = jobs.values().collect();
+        let bytes = serde_json::to_vec_pretty(&list)?;
+
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+pub fn init(config: &Config) -> Result<()> {
+    let store = JobStore::open(&config.server.download_path)?;
+    JOB_STORE
+        .set(store)
+        .map_err(|_| crate::error::BotError::InternalError("JOB_STORE init failed".to_string()))?;
+    info!(dir = %config.server.download_path, "任务日志已加载");
+    Ok(())
+}
+
+pub fn store() -> &'static JobStore {
+    JOB_STORE.get().expect("JOB_STORE not initialized")
+}
+
+/// 任务表尚未初始化时返回 None（如仅启动 Web 服务的测试）
+pub fn try_store() -> Option<&'static JobStore> {
+    JOB_STORE.get()
+}
@@ -0,0 +1,285 @@
+use crate::config::Config;
+use crate::error::Result;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+static LINK_STORE: OnceLock<LinkStore> = OnceLock::new();
+
+static COUNTER_FILE: &str = ".links.json";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 无状态的签名下载链接：相对下载目录的路径、过期时间（unix 秒）、最多下载次数（0 不限）
+/// 与三者的 HMAC-SHA256 签名，服务重启后依然有效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedLink {
+    pub path: String,
+    pub exp: i64,
+    pub max: u32,
+    pub sig: String,
+}
+
+impl SignedLink {
+    pub fn new(secret: &[u8], path: &str, exp: i64, max: u32) -> Self {
+        let sig = URL_SAFE_NO_PAD.encode(Self::mac(secret, path, exp, max).finalize().into_bytes());
+        Self { path: path.to_string(), exp, max, sig }
+    }
+
+    fn mac(secret: &[u8], path: &str, exp: i64, max: u32) -> HmacSha256 {
+        keyed(secret, &format!("download\n{}\n{}\n{}", path, exp, max))
+    }
+
+    /// 常数时间比较签名
+    pub fn verify(&self, secret: &[u8]) -> bool {
+        check(Self::mac(secret, &self.path, self.exp, self.max), &self.sig)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.exp
+    }
+
+    /// 链接的查询串
+    pub fn query(&self) -> String {
+        format!(
+            "path={}&exp={}&max={}&sig={}",
+            utf8_percent_encode(&self.path, NON_ALPHANUMERIC),
+            self.exp,
+            self.max,
+            self.sig
+        )
+    }
+}
+
+/// 在线阅读链接：作品 id 与过期时间的签名，与下载链接使用不同的签名域
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaderLink {
+    pub aid: i64,
+    pub exp: i64,
+    pub sig: String,
+}
+
+impl ReaderLink {
+    pub fn new(secret: &[u8], aid: i64, exp: i64) -> Self {
+        let sig = URL_SAFE_NO_PAD.encode(Self::mac(secret, aid, exp).finalize().into_bytes());
+        Self { aid, exp, sig }
+    }
+
+    fn mac(secret: &[u8], aid: i64, exp: i64) -> HmacSha256 {
+        keyed(secret, &format!("read\n{}\n{}", aid, exp))
+    }
+
+    pub fn verify(&self, secret: &[u8]) -> bool {
+        check(Self::mac(secret, self.aid, self.exp), &self.sig)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.exp
+    }
+
+    /// 链接的查询串
+    pub fn query(&self) -> String {
+        format!("exp={}&sig={}", self.exp, self.sig)
+    }
+}
+
+fn keyed(secret: &[u8], message: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(message.as_bytes());
+    mac
+}
+
+fn check(mac: HmacSha256, sig: &str) -> bool {
+    let Ok(sig) = URL_SAFE_NO_PAD.decode(sig) else {
+        return false;
+    };
+    mac.verify_slice(&sig).is_ok()
+}
+
+/// 图片代理地址的签名：只有本服务页面中生成的地址才能经由代理访问
+pub fn image_sig(secret: &[u8], url: &str) -> String {
+    URL_SAFE_NO_PAD.encode(image_mac(secret, url).finalize().into_bytes())
+}
+
+fn image_mac(secret: &[u8], url: &str) -> HmacSha256 {
+    keyed(secret, &format!("image\n{}", url))
+}
+
+/// 已下载次数，过期的记录在下次写入时清理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Usage {
+    exp: i64,
+    count: u32,
+}
+
+/// 落盘的链接状态：限次链接的下载计数，以及已发出链接的文件（相对路径）与最晚过期时间
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LinkState {
+    #[serde(default)]
+    usage: HashMap<String, Usage>,
+    #[serde(default)]
+    issued: HashMap<String, i64>,
+}
+
+impl LinkState {
+    fn prune(&mut self, now: i64) {
+        self.usage.retain(|_, u| u.exp > now);
+        self.issued.retain(|_, exp| *exp > now);
+    }
+}
+
+/// 签名密钥与已发出链接的状态，状态落盘以便重启后仍然生效
+pub struct LinkStore {
+    path: PathBuf,
+    secret: Vec<u8>,
+    state: Mutex<LinkState>,
+}
+
+impl LinkStore {
+    pub fn open(dir: &str, secret: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = PathBuf::from(dir).join(COUNTER_FILE);
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                // 旧版本只保存下载计数
+                .or_else(|_| {
+                    serde_json::from_slice(&bytes)
+                        .map(|usage| LinkState { usage, ..Default::default() })
+                })
+                .unwrap_or_else(|e| {
+                    warn!(path = %path.display(), error = %e, "链接状态文件损坏，已忽略");
+                    LinkState::default()
+                }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => LinkState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, secret: secret.as_bytes().to_vec(), state: Mutex::new(state) })
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    pub fn verify(&self, link: &SignedLink) -> bool {
+        link.verify(&self.secret)
+    }
+
+    pub fn verify_reader(&self, link: &ReaderLink) -> bool {
+        link.verify(&self.secret)
+    }
+
+    pub fn verify_image(&self, url: &str, sig: &str) -> bool {
+        check(image_mac(&self.secret, url), sig)
+    }
+
+    /// 记录一次下载；链接不限次数时直接放行，已达上限时返回 false
+    pub async fn record(&self, link: &SignedLink) -> Result<bool> {
+        if link.max == 0 {
+            return Ok(true);
+        }
+        let mut state = self.state.lock().await;
+        state.prune(chrono::Utc::now().timestamp());
+
+        let entry =
+            state.usage.entry(link.sig.clone()).or_insert(Usage { exp: link.exp, count: 0 });
+        if entry.count >= link.max {
+            return Ok(false);
+        }
+        entry.count += 1;
+        self.persist(&state).await?;
+        Ok(true)
+    }
+
+    /// 登记已发出的链接，过期前清理任务不会删除对应文件
+    pub async fn issue(&self, link: &SignedLink) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state.prune(chrono::Utc::now().timestamp());
+        let exp = state.issued.entry(link.path.clone()).or_insert(link.exp);
+        *exp = (*exp).max(link.exp);
+        self.persist(&state).await
+    }
+
+    /// 仍有未过期链接的文件（相对下载目录的路径）
+    pub async fn active_paths(&self) -> Vec<String> {
+        let now = chrono::Utc::now().timestamp();
+        let state = self.state.lock().await;
+        state.issued.iter().filter(|(_, exp)| **exp > now).map(|(path, _)| path.clone()).collect()
+    }
+
+    async fn persist(&self, state: &LinkState) -> Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(state)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// 为下载目录中的文件生成签名链接并登记，文件不在下载目录内时返回 None
+pub async fn sign_file(config: &Config, file: &str) -> Option<SignedLink> {
+    let relative = Path::new(file).strip_prefix(&config.server.download_path).ok()?;
+    let exp = chrono::Utc::now().timestamp() + config.server.link_ttl_hours as i64 * 3600;
+    let max = config.server.link_max_downloads;
+    let link = SignedLink::new(store().secret(), relative.to_str()?, exp, max);
+    if let Err(e) = store().issue(&link).await {
+        warn!(path = %link.path, error = %e, "下载链接登记失败");
+    }
+    Some(link)
+}
+
+pub fn download_url(config: &Config, link: &SignedLink) -> String {
+    let host = config.server.web_host.trim_end_matches('/');
+    format!("{}/download?{}", host, link.query())
+}
+
+/// 作品的在线阅读链接，有效期为 `reader_ttl_hours`
+pub fn reader_url(config: &Config, aid: i64) -> String {
+    let exp = chrono::Utc::now().timestamp() + config.server.reader_ttl_hours as i64 * 3600;
+    let link = ReaderLink::new(store().secret(), aid, exp);
+    let host = config.server.web_host.trim_end_matches('/');
+    format!("{}/read/{}?{}", host, aid, link.query())
+}
+
+/// `web_host` 是否可从外部访问；默认的 localhost 等回环地址无法作为 Telegram 按钮链接
+pub fn web_host_public(config: &Config) -> bool {
+    let Ok(url) = reqwest::Url::parse(&config.server.web_host) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    match host.trim_matches(['[', ']']).parse::<std::net::IpAddr>() {
+        Ok(ip) => !ip.is_loopback() && !ip.is_unspecified(),
+        Err(_) => {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            host != "localhost" && !host.ends_with(".localhost")
+        }
+    }
+}
+
+pub fn init(config: &Config) -> Result<()> {
+    let secret = if config.server.link_secret.is_empty() {
+        // 未配置密钥时每次启动随机生成，已发出的链接在重启后失效
+        warn!("未配置 link_secret，下载链接在服务重启后失效");
+        uuid::Uuid::new_v4().simple().to_string()
+    } else {
+        config.server.link_secret.clone()
+    };
+    let store = LinkStore::open(&config.server.download_path, &secret)?;
+    LINK_STORE
+        .set(store)
+        .map_err(|_| crate::error::BotError::InternalError("LINK_STORE init failed".to_string()))?;
+    info!("下载链接签名已初始化");
+    Ok(())
+}
+
+pub fn store() -> &'static LinkStore {
+    LINK_STORE.get().expect("LINK_STORE not initialized")
+}
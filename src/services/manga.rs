@@ -1,5 +1,6 @@
 use crate::error::BotError;
 use crate::models::{MangaDetail, MangaInfo};
+use crate::services::health;
 use crate::utils;
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -77,11 +78,12 @@ pub async fn parse_rank(url: &str, base_url: &str) -> Result<Vec<MangaInfo>, Bot
             mangas.push(MangaInfo { id, rank, title, cover, author, total, fav, published });
         }
     }
+    health::record_parse(mangas.len());
     Ok(mangas)
 }
 
 pub async fn parse_detail(id: i64, url: &str, base_url: &str) -> Result<MangaDetail, BotError> {
-    if let Some(detail) = utils::cache::info_cache().get(&id.to_string()).await {
+    if let Some(detail) = utils::cache::lookup(utils::cache::info_cache(), &id.to_string()).await {
         info!("id:{} get manga detail from cache", id);
         return Ok(detail);
     }
@@ -215,6 +217,7 @@ pub async fn parse_cate(url: &str, base_url: &str) -> Result<Vec<MangaInfo>, Bot
         });
     }
 
+    health::record_parse(mangas.len());
     Ok(mangas)
 }
 
@@ -225,7 +228,7 @@ pub async fn extract_image_urls(
     url: &str,
     base_url: &str,
 ) -> Result<Vec<String>, BotError> {
-    if let Some(images) = utils::cache::image_cache().get(aid).await {
+    if let Some(images) = utils::cache::lookup(utils::cache::image_cache(), aid).await {
         info!("aid:{} Image cache hit", aid);
         return Ok(images);
     }
@@ -304,6 +307,7 @@ pub async fn parse_search(url: &str, base_url: &str) -> Result<Vec<MangaInfo>, B
         });
     }
 
+    health::record_parse(mangas.len());
     Ok(mangas)
 }
 // 已统一使用 utils::http::resolve_url
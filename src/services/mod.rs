@@ -1,2 +1,13 @@
+pub mod api;
+pub mod health;
+pub mod jobs;
+pub mod links;
 pub mod manga;
+pub mod opds;
+pub mod proxy;
+pub mod reader;
+pub mod retention;
+pub mod scheduler;
+pub mod shutdown;
 pub mod web;
+pub mod webhook;
@@ -0,0 +1,378 @@
+use crate::bot::commands::cate::{
+    Category, DoujinshiSub, ShortSub, TankoubonSub, WebtoonSub, build_cate_url,
+};
+use crate::bot::commands::rank::{RankType, build_ranking_url};
+use crate::bot::commands::search::build_search_url;
+use crate::bot::commands::{build_images_url, info::build_info_url};
+use crate::config::Config;
+use crate::error::BotError;
+use crate::models::{ArchiveStream, MangaInfo};
+use crate::services::api;
+use crate::services::manga;
+use crate::services::proxy::image_src;
+use crate::services::web::stream_archive;
+use crate::utils::escape_xml;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderValue, WWW_AUTHENTICATE};
+use actix_web::{HttpRequest, HttpResponse, web};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use strum::IntoEnumIterator;
+use tracing::error;
+
+static NAVIGATION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+static ACQUISITION_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+static OPENSEARCH_TYPE: &str = "application/opensearchdescription+xml";
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    q: Option<String>,
+    page: Option<i32>,
+}
+
+/// 导航条目：标题、说明与目标 feed 及其类型
+struct NavEntry {
+    id: String,
+    title: String,
+    content: String,
+    href: String,
+    kind: &'static str,
+}
+
+/// 分类树：（分类代码、子分类代码），与 /cate 命令的参数一致
+fn categories() -> Vec<(&'static str, &'static str)> {
+    let mut list = Vec::new();
+    list.extend(DoujinshiSub::iter().map(|s| ("trz", s.as_str())));
+    list.extend(TankoubonSub::iter().map(|s| ("dxb", s.as_str())));
+    list.extend(ShortSub::iter().map(|s| ("dp", s.as_str())));
+    list.extend(WebtoonSub::iter().map(|s| ("hm", s.as_str())));
+    list
+}
+
+/// 与 JSON 接口共用 API 密钥；阅读器通过 Basic 认证提交，密码为密钥，
+/// 401 时附带 `WWW-Authenticate` 让阅读器弹出登录框
+fn authorize(req: &HttpRequest, config: &Config) -> Option<HttpResponse> {
+    let mut denied = api::authorize(req, config)?;
+    if denied.status() == StatusCode::UNAUTHORIZED {
+        denied
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Basic realm=\"mangabot\""));
+    }
+    Some(denied)
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+fn feed_head(id: &str, title: &str, self_href: &str, kind: &str) -> String {
+    let mut xml = String::new();
+    let _ = write!(
+        xml,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:opds=\"http://opds-spec.org/2010/catalog\">\n\
+         <id>{}</id>\n<title>{}</title>\n<updated>{}</updated>\n\
+         <author><name>mangabot</name></author>\n\
+         <link rel=\"self\" href=\"{}\" type=\"{}\"/>\n\
+         <link rel=\"start\" href=\"/opds\" type=\"{}\"/>\n\
+         <link rel=\"search\" href=\"/opds/opensearch.xml\" type=\"{}\"/>\n",
+        escape_xml(id),
+        escape_xml(title),
+        now(),
+        escape_xml(self_href),
+        kind,
+        NAVIGATION_TYPE,
+        OPENSEARCH_TYPE
+    );
+    xml
+}
+
+fn xml_response(kind: &str, xml: String) -> HttpResponse {
+    HttpResponse::Ok().content_type(kind).body(xml)
+}
+
+/// 导航 feed，条目指向下一级目录或作品列表
+fn navigation_feed(id: &str, title: &str, self_href: &str, entries: &[NavEntry]) -> String {
+    let mut xml = feed_head(id, title, self_href, NAVIGATION_TYPE);
+    let updated = now();
+    for entry in entries {
+        let _ = write!(
+            xml,
+            "<entry>\n<id>{}</id>\n<title>{}</title>\n<updated>{}</updated>\n\
+             <content type=\"text\">{}</content>\n<link rel=\"subsection\" href=\"{}\" type=\"{}\"/>\n</entry>\n",
+            escape_xml(&entry.id),
+            escape_xml(&entry.title),
+            updated,
+            escape_xml(&entry.content),
+            escape_xml(&entry.href),
+            entry.kind
+        );
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// 作品列表 feed：每部作品一个获取链接，`next_href` 指向下一页
+pub fn acquisition_feed(
+    id: &str,
+    title: &str,
+    self_href: &str,
+    next_href: Option<&str>,
+    mangas: &[MangaInfo],
+) -> String {
+    let mut xml = feed_head(id, title, self_href, ACQUISITION_TYPE);
+    if let Some(next) = next_href {
+        let _ = writeln!(
+            xml,
+            "<link rel=\"next\" href=\"{}\" type=\"{}\"/>",
+            escape_xml(next),
+            ACQUISITION_TYPE
+        );
+    }
+    let updated = now();
+    for m in mangas {
+        let _ = write!(
+            xml,
+            "<entry>\n<id>urn:mangabot:{}</id>\n<title>{}</title>\n<updated>{}</updated>\n",
+            m.id,
+            escape_xml(&m.title),
+            updated
+        );
+        if !m.author.is_empty() {
+            let _ = writeln!(xml, "<author><name>{}</name></author>", escape_xml(&m.author));
+        }
+        let _ = writeln!(xml, "<content type=\"text\">📄 {} 页</content>", m.total.max(0));
+        if !m.cover.is_empty() {
+            let cover = escape_xml(&image_src(&m.cover));
+            let _ = write!(
+                xml,
+                "<link rel=\"http://opds-spec.org/image\" href=\"{0}\" type=\"image/jpeg\"/>\n\
+                 <link rel=\"http://opds-spec.org/image/thumbnail\" href=\"{0}\" type=\"image/jpeg\"/>\n",
+                cover
+            );
+        }
+        let _ = write!(
+            xml,
+            "<link rel=\"http://opds-spec.org/acquisition\" href=\"/opds/download/{}\" type=\"application/zip\"/>\n</entry>\n",
+            m.id
+        );
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn upstream_error(e: BotError) -> HttpResponse {
+    error!(error = %e, "opds request failed");
+    HttpResponse::BadGateway().body("failed to load catalog")
+}
+
+async fn root(req: HttpRequest, config: web::Data<Config>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let entries = [
+        NavEntry {
+            id: "urn:mangabot:rank".to_string(),
+            title: "排行榜".to_string(),
+            content: "日榜、周榜、月榜".to_string(),
+            href: "/opds/rank".to_string(),
+            kind: NAVIGATION_TYPE,
+        },
+        NavEntry {
+            id: "urn:mangabot:cate".to_string(),
+            title: "分类".to_string(),
+            content: "同人志、单行本、短篇、韩漫".to_string(),
+            href: "/opds/cate".to_string(),
+            kind: NAVIGATION_TYPE,
+        },
+    ];
+    xml_response(
+        NAVIGATION_TYPE,
+        navigation_feed("urn:mangabot:root", "mangabot", "/opds", &entries),
+    )
+}
+
+async fn rank_index(req: HttpRequest, config: web::Data<Config>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let entries: Vec<NavEntry> = RankType::iter()
+        .map(|t| NavEntry {
+            id: format!("urn:mangabot:rank:{}", t.as_str()),
+            title: t.as_name().to_string(),
+            content: format!("收藏排行 {}", t.as_name()),
+            href: format!("/opds/rank/{}", t.as_str()),
+            kind: ACQUISITION_TYPE,
+        })
+        .collect();
+    xml_response(
+        NAVIGATION_TYPE,
+        navigation_feed("urn:mangabot:rank", "排行榜", "/opds/rank", &entries),
+    )
+}
+
+async fn rank(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    period: web::Path<String>,
+    query: web::Query<PageQuery>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let rank_type = RankType::from_str(&period).unwrap_or(RankType::Day);
+    let page = query.page.unwrap_or(1).clamp(1, 1000);
+    let url = build_ranking_url(&config.manga.base_url, rank_type, page);
+    let mangas = match manga::parse_rank(&url, &config.manga.base_url).await {
+        Ok(mangas) => mangas,
+        Err(e) => return upstream_error(e),
+    };
+    let base = format!("/opds/rank/{}", rank_type.as_str());
+    xml_response(
+        ACQUISITION_TYPE,
+        list_feed(
+            &format!("urn:mangabot:rank:{}", rank_type.as_str()),
+            rank_type.as_name(),
+            &base,
+            page,
+            &mangas,
+        ),
+    )
+}
+
+async fn cate_index(req: HttpRequest, config: web::Data<Config>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let entries: Vec<NavEntry> = categories()
+        .into_iter()
+        .map(|(cate, sub)| {
+            let (name, _) = Category::from_str(cate, sub).to_cate_info();
+            NavEntry {
+                id: format!("urn:mangabot:cate:{}:{}", cate, sub),
+                content: name.clone(),
+                title: name,
+                href: format!("/opds/cate/{}/{}", cate, sub),
+                kind: ACQUISITION_TYPE,
+            }
+        })
+        .collect();
+    xml_response(
+        NAVIGATION_TYPE,
+        navigation_feed("urn:mangabot:cate", "分类", "/opds/cate", &entries),
+    )
+}
+
+async fn cate(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    path: web::Path<(String, String)>,
+    query: web::Query<PageQuery>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let (cate, sub) = path.into_inner();
+    let category = Category::from_str(&cate, &sub);
+    let (name, cate_num) = category.to_cate_info();
+    let page = query.page.unwrap_or(1).clamp(1, 1000);
+    let url = build_cate_url(&config.manga.base_url, cate_num, page);
+    let mangas = match manga::parse_cate(&url, &config.manga.base_url).await {
+        Ok(mangas) => mangas,
+        Err(e) => return upstream_error(e),
+    };
+    let base = format!("/opds/cate/{}/{}", cate, sub);
+    xml_response(
+        ACQUISITION_TYPE,
+        list_feed(&format!("urn:mangabot:cate:{}", cate_num), &name, &base, page, &mangas),
+    )
+}
+
+async fn search(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    query: web::Query<PageQuery>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let Some(key) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) else {
+        return HttpResponse::BadRequest().body("missing q");
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let url = build_search_url(&config.manga.base_url, key, "a", page);
+    let mangas = match manga::parse_search(&url, &config.manga.base_url).await {
+        Ok(mangas) => mangas,
+        Err(e) => return upstream_error(e),
+    };
+    let base = format!("/opds/search?q={}", utf8_percent_encode(key, NON_ALPHANUMERIC));
+    xml_response(
+        ACQUISITION_TYPE,
+        list_feed("urn:mangabot:search", &format!("搜索:{}", key), &base, page, &mangas),
+    )
+}
+
+/// 分页的作品列表；本页为空时不再提供下一页
+fn list_feed(id: &str, title: &str, base: &str, page: i32, mangas: &[MangaInfo]) -> String {
+    let sep = if base.contains('?') { '&' } else { '?' };
+    let self_href = format!("{}{}page={}", base, sep, page);
+    let next_href = format!("{}{}page={}", base, sep, page + 1);
+    let next = (!mangas.is_empty()).then_some(next_href.as_str());
+    acquisition_feed(&format!("{}:{}", id, page), title, &self_href, next, mangas)
+}
+
+/// OpenSearch 描述文件，阅读器据此构造搜索请求
+async fn opensearch(req: HttpRequest, config: web::Data<Config>) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n\
+         <ShortName>mangabot</ShortName>\n<Description>搜索作品</Description>\n\
+         <InputEncoding>UTF-8</InputEncoding>\n<OutputEncoding>UTF-8</OutputEncoding>\n\
+         <Url type=\"{}\" template=\"/opds/search?q={{searchTerms}}\"/>\n\
+         </OpenSearchDescription>\n",
+        ACQUISITION_TYPE
+    );
+    xml_response(OPENSEARCH_TYPE, xml)
+}
+
+/// 获取链接：与在线打包相同，边抓取边输出 zip
+async fn download(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    aid: web::Path<i64>,
+) -> HttpResponse {
+    if let Some(denied) = authorize(&req, &config) {
+        return denied;
+    }
+    let aid = aid.into_inner();
+    let base_url = &config.manga.base_url;
+    let sid = aid.to_string();
+    let detail = manga::parse_detail(aid, &build_info_url(base_url, &sid), base_url).await;
+    let images = manga::extract_image_urls(&sid, &build_images_url(base_url, &sid), base_url).await;
+    match (detail, images) {
+        (Ok(detail), Ok(images)) if !images.is_empty() => {
+            let title = if detail.title.is_empty() { sid } else { detail.title };
+            stream_archive(ArchiveStream { title, images }, config.server.download_concurrency)
+        }
+        (Ok(_), Ok(_)) => HttpResponse::NotFound().body("no pages"),
+        (Err(e), _) | (_, Err(e)) => upstream_error(e),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/opds")
+            .route("", web::get().to(root))
+            .route("/opensearch.xml", web::get().to(opensearch))
+            .route("/search", web::get().to(search))
+            .route("/rank", web::get().to(rank_index))
+            .route("/rank/{period}", web::get().to(rank))
+            .route("/cate", web::get().to(cate_index))
+            .route("/cate/{cate}/{sub}", web::get().to(cate))
+            .route("/download/{aid}", web::get().to(download)),
+    );
+}
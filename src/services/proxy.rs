@@ -0,0 +1,266 @@
+use crate::config::Config;
+use crate::error::{BotError, Result};
+use crate::services::links;
+use crate::utils::{http, img};
+use actix_web::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use actix_web::{HttpRequest, HttpResponse, web};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+static IMAGE_PROXY: OnceLock<ImageProxy> = OnceLock::new();
+
+/// 图片内容按 URL 摘要命名，内容不变，浏览器可长期缓存
+static CACHE_CONTROL_VALUE: &str = "public, max-age=604800, immutable";
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    size: u64,
+    /// 最近访问序号，越小越久未访问
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    entries: HashMap<String, Entry>,
+    total: u64,
+    clock: u64,
+}
+
+impl Index {
+    fn touch(&mut self, key: &str) -> bool {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|e| e.used = clock).is_some()
+    }
+
+    fn insert(&mut self, key: String, size: u64) {
+        self.clock += 1;
+        if let Some(old) = self.entries.insert(key, Entry { size, used: self.clock }) {
+            self.total -= old.size;
+        }
+        self.total += size;
+    }
+
+    /// 按最近最少使用淘汰，直到总大小不超过 `max_bytes`，返回被淘汰的键
+    fn evict(&mut self, max_bytes: u64) -> Vec<String> {
+        let mut order: Vec<(String, Entry)> =
+            self.entries.iter().map(|(k, e)| (k.clone(), *e)).collect();
+        order.sort_by_key(|(_, e)| e.used);
+        let mut evicted = Vec::new();
+        for (key, entry) in order {
+            if self.total <= max_bytes {
+                break;
+            }
+            self.entries.remove(&key);
+            self.total -= entry.size;
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+/// 图片代理的磁盘缓存，总大小超过上限时按 LRU 淘汰
+pub struct ImageProxy {
+    dir: PathBuf,
+    max_bytes: u64,
+    base_url: String,
+    allowed_hosts: Vec<String>,
+    index: Mutex<Index>,
+}
+
+impl ImageProxy {
+    /// 打开缓存目录，按文件修改时间恢复访问顺序
+    pub fn open(
+        dir: &str,
+        max_bytes: u64,
+        base_url: &str,
+        allowed_hosts: &[String],
+    ) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if meta.is_file() && name.ends_with(".part") {
+                // 上次运行中断时未写完的临时文件
+                let _ = std::fs::remove_file(entry.path());
+                continue;
+            }
+            if !meta.is_file() || !is_key(&name) {
+                continue;
+            }
+            let modified = meta.modified().unwrap_or(std::time::UNIX_EPOCH);
+            files.push((modified, name, meta.len()));
+        }
+        files.sort();
+
+        let mut index = Index::default();
+        for (_, key, size) in files {
+            index.insert(key, size);
+        }
+        info!(dir, entries = index.entries.len(), bytes = index.total, "图片缓存已加载");
+
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            max_bytes,
+            base_url: base_url.to_string(),
+            allowed_hosts: allowed_hosts.to_vec(),
+            index: Mutex::new(index),
+        })
+    }
+
+    pub fn is_allowed(&self, url: &str) -> bool {
+        http::host_allowed(url, &self.base_url, &self.allowed_hosts)
+    }
+
+    /// 读取缓存的图片，未命中时从源站下载、校验后写入缓存
+    pub async fn get(&self, url: &str) -> Result<(String, Vec<u8>)> {
+        if !self.is_allowed(url) {
+            return Err(BotError::InternalError("SSRF blocked: host not allowed".to_string()));
+        }
+        let key = cache_key(url);
+        let path = self.dir.join(&key);
+
+        if self.index.lock().await.touch(&key) {
+            match tokio::fs::read(&path).await {
+                Ok(bytes) => return Ok((key, bytes)),
+                Err(e) => warn!(path = %path.display(), error = %e, "图片缓存读取失败"),
+            }
+        }
+
+        let bytes = http::fetch_bytes(url).await?;
+        img::verify_image(&bytes)?;
+        self.store(&key, &path, &bytes).await?;
+        Ok((key, bytes))
+    }
+
+    async fn store(&self, key: &str, path: &Path, bytes: &[u8]) -> Result<()> {
+        // 同一地址可能同时未命中，各自写入独立的临时文件再 rename
+        let tmp = self.dir.join(format!("{}.{}.part", key, uuid::Uuid::new_v4().simple()));
+        tokio::fs::write(&tmp, bytes).await?;
+        if let Err(e) = tokio::fs::rename(&tmp, path).await {
+            tokio::fs::remove_file(&tmp).await.ok();
+            return Err(e.into());
+        }
+
+        let evicted = {
+            let mut index = self.index.lock().await;
+            index.insert(key.to_string(), bytes.len() as u64);
+            if self.max_bytes > 0 { index.evict(self.max_bytes) } else { Vec::new() }
+        };
+        for key in evicted {
+            if let Err(e) = tokio::fs::remove_file(self.dir.join(&key)).await {
+                warn!(key, error = %e, "图片缓存淘汰失败");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn cache_key(url: &str) -> String {
+    Sha256::digest(url.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn is_key(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// 经由图片代理访问的相对地址，带签名
+pub fn proxy_url(url: &str) -> String {
+    format!(
+        "/img?url={}&sig={}",
+        utf8_percent_encode(url, NON_ALPHANUMERIC),
+        links::image_sig(links::store().secret(), url)
+    )
+}
+
+/// 阅读页与 OPDS 目录中的图片地址：源站在允许列表中时经由代理，否则直接引用原地址
+pub fn image_src(url: &str) -> String {
+    if cache().is_allowed(url) { proxy_url(url) } else { url.to_string() }
+}
+
+/// 经由图片代理访问的完整地址，供 Telegram 等外部客户端拉取；
+/// `web_host` 不可从外部访问或源站不在允许列表中时返回原地址
+pub fn public_proxy_url(config: &Config, url: &str) -> String {
+    if !links::web_host_public(config) || !cache().is_allowed(url) {
+        return url.to_string();
+    }
+    format!("{}{}", config.server.web_host.trim_end_matches('/'), proxy_url(url))
+}
+
+#[derive(Deserialize)]
+pub struct ImageQuery {
+    url: String,
+    sig: String,
+}
+
+pub async fn handle_image(
+    req: HttpRequest,
+    query: web::Query<ImageQuery>,
+) -> actix_web::Result<HttpResponse> {
+    if !links::store().verify_image(&query.url, &query.sig) {
+        error!(url = %query.url, "invalid image signature");
+        return Ok(HttpResponse::Forbidden().body("invalid signature"));
+    }
+    let proxy = cache();
+    if !proxy.is_allowed(&query.url) {
+        error!(url = %query.url, "image host not allowed");
+        return Ok(HttpResponse::Forbidden().body("host not allowed"));
+    }
+
+    let etag = format!("\"{}\"", cache_key(&query.url));
+    let matched = req
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|t| t.trim() == etag || t.trim() == "*"));
+    if matched {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((ETAG, etag))
+            .insert_header((CACHE_CONTROL, CACHE_CONTROL_VALUE))
+            .finish());
+    }
+
+    match proxy.get(&query.url).await {
+        Ok((_, bytes)) => {
+            let ext = http::page_extension(&bytes, None, &query.url);
+            let mime = mime_guess::from_ext(ext).first_or_octet_stream();
+            Ok(HttpResponse::Ok()
+                .content_type(mime)
+                .insert_header((ETAG, etag))
+                .insert_header((CACHE_CONTROL, CACHE_CONTROL_VALUE))
+                .body(bytes))
+        }
+        Err(e) => {
+            error!(url = %query.url, error = %e, "image proxy fetch failed");
+            Ok(HttpResponse::BadGateway().body("failed to fetch image"))
+        }
+    }
+}
+
+pub fn init(config: &Config) -> Result<()> {
+    let server = &config.server;
+    let proxy = ImageProxy::open(
+        &server.image_cache_path,
+        server.image_cache_max_mb * 1024 * 1024,
+        &config.manga.base_url,
+        &server.proxy_allowed_hosts,
+    )?;
+    IMAGE_PROXY
+        .set(proxy)
+        .map_err(|_| BotError::InternalError("IMAGE_PROXY init failed".to_string()))?;
+    Ok(())
+}
+
+pub fn cache() -> &'static ImageProxy {
+    IMAGE_PROXY.get().expect("IMAGE_PROXY not initialized")
+}
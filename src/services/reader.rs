@@ -0,0 +1,182 @@
+use crate::bot::commands::{build_images_url, info::build_info_url};
+use crate::config::Config;
+use crate::models::MangaDetail;
+use crate::services::links::{self, ReaderLink};
+use crate::services::manga;
+use crate::services::proxy::image_src;
+use crate::utils::escape_xml;
+use actix_web::{HttpRequest, HttpResponse, web};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use tracing::{error, info};
+
+/// 阅读参数：签名 `exp`/`sig`，模式 `mode`（scroll/paged）与分页模式下的页码 `page`（从 1 开始）
+#[derive(Deserialize)]
+pub struct ReaderQuery {
+    exp: i64,
+    sig: String,
+    mode: Option<String>,
+    page: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadMode {
+    /// 所有页面纵向排列
+    Scroll,
+    /// 每次显示一页
+    Paged,
+}
+
+impl ReadMode {
+    pub fn parse(s: Option<&str>) -> Self {
+        match s {
+            Some("paged") | Some("page") => ReadMode::Paged,
+            _ => ReadMode::Scroll,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ReadMode::Scroll => "scroll",
+            ReadMode::Paged => "paged",
+        }
+    }
+}
+
+pub async fn handle_read(
+    req: HttpRequest,
+    aid: web::Path<i64>,
+    query: web::Query<ReaderQuery>,
+) -> actix_web::Result<HttpResponse> {
+    let aid = aid.into_inner();
+    let link = ReaderLink { aid, exp: query.exp, sig: query.sig.clone() };
+    if !links::store().verify_reader(&link) {
+        error!(aid, "invalid reader signature");
+        return Ok(HttpResponse::Forbidden().body("invalid signature"));
+    }
+    if link.is_expired(chrono::Utc::now().timestamp()) {
+        info!(aid, "reader link expired");
+        return Ok(HttpResponse::Gone().body("link expired"));
+    }
+
+    let Some(config) = req.app_data::<web::Data<Config>>() else {
+        return Ok(HttpResponse::InternalServerError().finish());
+    };
+    let base_url = &config.manga.base_url;
+    let sid = aid.to_string();
+    let detail = manga::parse_detail(aid, &build_info_url(base_url, &sid), base_url).await;
+    let images = manga::extract_image_urls(&sid, &build_images_url(base_url, &sid), base_url).await;
+    let (detail, images) = match (detail, images) {
+        (Ok(detail), Ok(images)) => (detail, images),
+        (Err(e), _) | (_, Err(e)) => {
+            error!(aid, error = %e, "reader fetch failed");
+            return Ok(HttpResponse::BadGateway().body("failed to load work"));
+        }
+    };
+
+    let mode = ReadMode::parse(query.mode.as_deref());
+    let html = render(&detail, &images, &link, mode, query.page.unwrap_or(1));
+    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(html))
+}
+
+/// 渲染阅读页：头部为作品信息，正文为页面图片
+pub fn render(
+    detail: &MangaDetail,
+    images: &[String],
+    link: &ReaderLink,
+    mode: ReadMode,
+    page: usize,
+) -> String {
+    let title = escape_xml(&detail.title);
+    let total = images.len();
+    let page = page.clamp(1, total.max(1));
+    let href = |mode: ReadMode, page: usize| {
+        escape_xml(&format!(
+            "/read/{}?{}&mode={}&page={}",
+            link.aid,
+            link.query(),
+            mode.as_str(),
+            page
+        ))
+    };
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"zh\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"referrer\" content=\"no-referrer\">\n<title>{title}</title>\n\
+         <style>{STYLE}</style>\n</head>\n<body>\n<header>\n<h1>{title}</h1>\n<p>"
+    );
+    let _ = write!(
+        html,
+        "👤 {} · 📚 {} · 📄 {} 页",
+        escape_xml(&detail.author),
+        escape_xml(&detail.category),
+        total
+    );
+    html.push_str("</p>\n");
+    if !detail.tags.is_empty() {
+        html.push_str("<p class=\"tags\">");
+        for tag in &detail.tags {
+            let _ = write!(html, "<span>#{}</span> ", escape_xml(tag));
+        }
+        html.push_str("</p>\n");
+    }
+    let _ = write!(
+        html,
+        "<nav><a href=\"{}\"{}>滚动</a> <a href=\"{}\"{}>分页</a></nav>\n</header>\n<main>\n",
+        href(ReadMode::Scroll, 1),
+        if mode == ReadMode::Scroll { " class=\"active\"" } else { "" },
+        href(ReadMode::Paged, page),
+        if mode == ReadMode::Paged { " class=\"active\"" } else { "" },
+    );
+
+    if images.is_empty() {
+        html.push_str("<p>暂无页面</p>\n");
+    } else if mode == ReadMode::Scroll {
+        for (i, url) in images.iter().enumerate() {
+            let _ = writeln!(
+                html,
+                "<img src=\"{}\" alt=\"{}\" loading=\"lazy\">",
+                escape_xml(&image_src(url)),
+                i + 1
+            );
+        }
+    } else {
+        let next = if page < total { href(ReadMode::Paged, page + 1) } else { String::new() };
+        let prev = if page > 1 { href(ReadMode::Paged, page - 1) } else { String::new() };
+        let _ = writeln!(
+            html,
+            "<a href=\"{}\"><img src=\"{}\" alt=\"{}\"></a>",
+            if next.is_empty() { "#" } else { &next },
+            escape_xml(&image_src(&images[page - 1])),
+            page
+        );
+        html.push_str("<nav class=\"pager\">");
+        if !prev.is_empty() {
+            let _ = write!(html, "<a id=\"prev\" href=\"{}\">上一页</a> ", prev);
+        }
+        let _ = write!(html, "<span>{} / {}</span>", page, total);
+        if !next.is_empty() {
+            let _ = write!(html, " <a id=\"next\" href=\"{}\">下一页</a>", next);
+        }
+        html.push_str("</nav>\n");
+        html.push_str(KEY_NAV);
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+static STYLE: &str = "body{margin:0;background:#111;color:#ddd;font-family:sans-serif}\
+header{padding:12px 16px;background:#1b1b1b}h1{font-size:1.2em;margin:0 0 6px}\
+p{margin:4px 0}.tags span{color:#8ab4f8}nav a{color:#aaa;margin-right:8px}\
+nav a.active{color:#fff;font-weight:bold}main{max-width:900px;margin:0 auto;text-align:center}\
+img{display:block;width:100%;height:auto;margin:0 auto}\
+.pager{padding:12px}.pager a{color:#fff}";
+
+/// 分页模式下左右方向键翻页
+static KEY_NAV: &str = "<script>document.addEventListener('keydown',function(e){\
+var a=document.getElementById(e.key==='ArrowLeft'?'prev':e.key==='ArrowRight'?'next':'');\
+if(a){location.href=a.href;}});</script>\n";
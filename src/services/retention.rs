@@ -0,0 +1,230 @@
+use crate::config::Config;
+use crate::error::Result;
+use crate::services::{jobs, links};
+use crate::utils::archive::ArchiveFormat;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+/// 分卷后缀 " (1of3)"、页码区间后缀 " [p1-20,35]" 与转码后缀 " [jpeg]"
+static VOLUME_SUFFIX: Lazy<Regex> = Lazy::new(|| Regex::new(r" \(\d+of\d+\)$").unwrap());
+static PAGES_SUFFIX: Lazy<Regex> = Lazy::new(|| Regex::new(r" \[p[\d,-]+\]$").unwrap());
+static TRANSCODE_SUFFIX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r" \[(original|jpeg|png)\]$").unwrap());
+
+/// 下载目录的保留策略，各项为 0 时不生效
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    pub max_bytes: u64,
+    pub max_age: Option<Duration>,
+    pub keep_recent: usize,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        let server = &config.server;
+        Self {
+            max_bytes: server.retention_max_mb * 1024 * 1024,
+            max_age: (server.retention_max_age_hours > 0)
+                .then(|| Duration::from_secs(server.retention_max_age_hours * 3600)),
+            keep_recent: server.retention_keep_recent,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes > 0 || self.max_age.is_some() || self.keep_recent > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictReason {
+    /// 超过最长保留时间
+    Expired,
+    /// 不在最近 N 部作品内
+    OverCount,
+    /// 总占用超出上限
+    OverQuota,
+}
+
+/// 同一作品在下载目录中的所有条目：页面目录、打包文件及其分卷
+#[derive(Debug, Clone)]
+pub struct WorkUsage {
+    pub title: String,
+    pub paths: Vec<PathBuf>,
+    pub bytes: u64,
+    /// 最近一次写入时间，视为最近下载时间
+    pub modified: SystemTime,
+}
+
+/// 下载目录条目所属的作品标题；隐藏文件（任务日志等）和无关文件返回 None
+pub fn work_key(name: &str, is_dir: bool) -> Option<String> {
+    if name.starts_with('.') {
+        return None;
+    }
+    if is_dir {
+        return Some(name.to_string());
+    }
+
+    let (stem, ext) = name.rsplit_once('.')?;
+    ArchiveFormat::parse(ext)?;
+    let stem = VOLUME_SUFFIX.replace(stem, "");
+    let stem = PAGES_SUFFIX.replace(&stem, "");
+    let stem = TRANSCODE_SUFFIX.replace(&stem, "");
+    Some(stem.to_string())
+}
+
+/// 按作品汇总下载目录的占用
+pub fn scan(dir: &Path) -> Result<Vec<WorkUsage>> {
+    let mut works: Vec<WorkUsage> = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(title) = work_key(&name, meta.is_dir()) else {
+            continue;
+        };
+
+        let (bytes, modified) = if meta.is_dir() {
+            WalkDir::new(entry.path())
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.metadata().ok())
+                .fold((0, meta.modified()?), |(bytes, modified), m| {
+                    let file_bytes = if m.is_file() { m.len() } else { 0 };
+                    (bytes + file_bytes, m.modified().map_or(modified, |t| t.max(modified)))
+                })
+        } else {
+            (meta.len(), meta.modified()?)
+        };
+
+        match works.iter_mut().find(|w| w.title == title) {
+            Some(work) => {
+                work.paths.push(entry.path());
+                work.bytes += bytes;
+                work.modified = work.modified.max(modified);
+            }
+            None => works.push(WorkUsage { title, paths: vec![entry.path()], bytes, modified }),
+        }
+    }
+    Ok(works)
+}
+
+/// 计算需要清理的作品（`works` 中的下标）：先按时间与数量淘汰，
+/// 仍超出容量时从最久未下载的作品开始清理；`protected` 中的作品始终保留
+pub fn plan(
+    works: &[WorkUsage],
+    policy: &RetentionPolicy,
+    protected: &HashSet<String>,
+    now: SystemTime,
+) -> Vec<(usize, EvictReason)> {
+    let mut order: Vec<usize> = (0..works.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(works[i].modified));
+
+    let mut total: u64 = works.iter().map(|w| w.bytes).sum();
+    let mut evict = Vec::new();
+    for (rank, &i) in order.iter().enumerate() {
+        let work = &works[i];
+        if protected.contains(&work.title) {
+            continue;
+        }
+        let age = now.duration_since(work.modified).unwrap_or_default();
+        let reason = if policy.max_age.is_some_and(|max| age > max) {
+            EvictReason::Expired
+        } else if policy.keep_recent > 0 && rank >= policy.keep_recent {
+            EvictReason::OverCount
+        } else {
+            continue;
+        };
+        evict.push((i, reason));
+        total -= work.bytes;
+    }
+
+    if policy.max_bytes > 0 {
+        for &i in order.iter().rev() {
+            if total <= policy.max_bytes {
+                break;
+            }
+            let work = &works[i];
+            if protected.contains(&work.title) || evict.iter().any(|(e, _)| *e == i) {
+                continue;
+            }
+            evict.push((i, EvictReason::OverQuota));
+            total -= work.bytes;
+        }
+    }
+    evict
+}
+
+/// 仍被引用的作品：下载中的任务（合并打包时为其中每部作品），以及签名链接尚未过期的打包文件
+async fn protected_titles(dir: &Path) -> HashSet<String> {
+    let mut titles: HashSet<String> =
+        jobs::store().list().await.iter().flat_map(|j| j.titles()).collect();
+    for relative in links::store().active_paths().await {
+        let path = dir.join(&relative);
+        if path.parent() != Some(dir) {
+            continue;
+        }
+        if let Some(title) =
+            path.file_name().and_then(|n| work_key(&n.to_string_lossy(), path.is_dir()))
+        {
+            titles.insert(title);
+        }
+    }
+    titles
+}
+
+/// 执行一轮清理，返回释放的字节数
+pub async fn run_once(config: &Config, policy: &RetentionPolicy) -> Result<u64> {
+    let dir = PathBuf::from(&config.server.download_path);
+    let protected = protected_titles(&dir).await;
+    let works = tokio::task::spawn_blocking({
+        let dir = dir.clone();
+        move || scan(&dir)
+    })
+    .await
+    .map_err(|e| crate::error::BotError::InternalError(e.to_string()))??;
+
+    let mut freed = 0;
+    for (i, reason) in plan(&works, policy, &protected, SystemTime::now()) {
+        let work = &works[i];
+        for path in &work.paths {
+            let removed = if path.is_dir() {
+                tokio::fs::remove_dir_all(path).await
+            } else {
+                tokio::fs::remove_file(path).await
+            };
+            if let Err(e) = removed {
+                error!(path = %path.display(), error = %e, "清理下载文件失败");
+            }
+        }
+        freed += work.bytes;
+        info!(title = %work.title, bytes = work.bytes, ?reason, "已清理作品下载文件");
+    }
+    Ok(freed)
+}
+
+/// 启动后台清理任务，未配置任何策略时不启动
+pub fn spawn(config: Config) {
+    let policy = RetentionPolicy::from_config(&config);
+    if !policy.is_enabled() {
+        info!("未配置下载目录清理策略");
+        return;
+    }
+
+    let interval = Duration::from_secs(config.server.retention_interval_minute.max(1) * 60);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match run_once(&config, &policy).await {
+                Ok(0) => {}
+                Ok(freed) => info!(bytes = freed, "下载目录清理完成"),
+                Err(e) => error!(error = %e, "下载目录清理失败"),
+            }
+        }
+    });
+}
@@ -0,0 +1,175 @@
+use crate::config::Config;
+use crate::error::Result;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::Notify;
+use tracing::info;
+
+static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+struct Slot {
+    job_id: String,
+    owner: i64,
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    running: Vec<Slot>,
+    waiting: Vec<Slot>,
+    seq: u64,
+}
+
+impl State {
+    /// 按调度顺序排列的等待任务：运行中任务最少的用户优先，同等条件下先到先得
+    fn projected_order(&self) -> Vec<&Slot> {
+        let mut load: HashMap<i64, usize> = HashMap::new();
+        for slot in &self.running {
+            *load.entry(slot.owner).or_default() += 1;
+        }
+
+        let mut pending: Vec<&Slot> = self.waiting.iter().collect();
+        let mut order = Vec::with_capacity(pending.len());
+        while let Some(i) = (0..pending.len())
+            .min_by_key(|&i| (load.get(&pending[i].owner).copied().unwrap_or(0), pending[i].seq))
+        {
+            let slot = pending.remove(i);
+            *load.entry(slot.owner).or_default() += 1;
+            order.push(slot);
+        }
+        order
+    }
+}
+
+/// 任务在调度器中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Running,
+    /// 排队中，从 1 开始
+    Queued(usize),
+}
+
+/// 全局下载调度器：限制同时运行的任务数，排队任务在用户之间轮转
+pub struct Scheduler {
+    max_running: usize,
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+impl Scheduler {
+    pub fn new(max_running: usize) -> Self {
+        Self { max_running: max_running.max(1), state: Default::default(), notify: Notify::new() }
+    }
+
+    /// 排队；返回的凭据被丢弃（如任务在排队时被取消）时自动出队
+    pub fn enqueue(&self, job_id: &str, owner: i64) -> Ticket<'_> {
+        let mut state = self.state.lock().unwrap();
+        state.seq += 1;
+        let seq = state.seq;
+        state.waiting.push(Slot { job_id: job_id.to_string(), owner, seq });
+        Ticket { scheduler: self, job_id: job_id.to_string(), started: false }
+    }
+
+    pub fn position(&self, job_id: &str) -> Option<Position> {
+        let state = self.state.lock().unwrap();
+        if state.running.iter().any(|s| s.job_id == job_id) {
+            return Some(Position::Running);
+        }
+        state
+            .projected_order()
+            .iter()
+            .position(|s| s.job_id == job_id)
+            .map(|i| Position::Queued(i + 1))
+    }
+
+    /// 轮到该任务且有空闲名额时转为运行
+    fn try_start(&self, job_id: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.running.len() >= self.max_running {
+            return false;
+        }
+        let next = state.projected_order().first().map(|s| s.job_id.clone());
+        if next.as_deref() != Some(job_id) {
+            return false;
+        }
+        let i = state.waiting.iter().position(|s| s.job_id == job_id).expect("queued job");
+        let slot = state.waiting.remove(i);
+        state.running.push(slot);
+        drop(state);
+        // 排在后面的任务可能已轮到，且仍有空闲名额
+        self.notify.notify_waiters();
+        true
+    }
+
+    fn release(&self, job_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.running.retain(|s| s.job_id != job_id);
+        state.waiting.retain(|s| s.job_id != job_id);
+        drop(state);
+        self.notify.notify_waiters();
+    }
+}
+
+/// 排队凭据
+pub struct Ticket<'a> {
+    scheduler: &'a Scheduler,
+    job_id: String,
+    started: bool,
+}
+
+impl<'a> Ticket<'a> {
+    /// 轮到该任务且有空闲名额时立即转为运行，否则返回 None 并继续排队
+    pub fn try_start(&mut self) -> Option<Permit<'a>> {
+        if self.started || !self.scheduler.try_start(&self.job_id) {
+            return None;
+        }
+        self.started = true;
+        Some(Permit { scheduler: self.scheduler, job_id: std::mem::take(&mut self.job_id) })
+    }
+
+    /// 等待运行名额，返回的许可释放时让出名额
+    pub async fn wait(mut self) -> Permit<'a> {
+        loop {
+            // 先登记通知再检查，避免错过检查与等待之间的释放或启动
+            let notified = self.scheduler.notify.notified();
+            if let Some(permit) = self.try_start() {
+                return permit;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        if !self.started {
+            self.scheduler.release(&self.job_id);
+        }
+    }
+}
+
+/// 运行许可
+pub struct Permit<'a> {
+    scheduler: &'a Scheduler,
+    job_id: String,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.job_id);
+    }
+}
+
+pub fn init(config: &Config) -> Result<()> {
+    let scheduler = Scheduler::new(config.server.max_concurrent_jobs);
+    SCHEDULER
+        .set(scheduler)
+        .map_err(|_| crate::error::BotError::InternalError("SCHEDULER init failed".to_string()))?;
+    info!(max_jobs = config.server.max_concurrent_jobs, "下载调度器初始化完成");
+    Ok(())
+}
+
+pub fn scheduler() -> &'static Scheduler {
+    SCHEDULER.get().expect("SCHEDULER not initialized")
+}
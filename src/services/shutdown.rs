@@ -0,0 +1,105 @@
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+static SHUTDOWN: Lazy<Shutdown> = Lazy::new(Shutdown::default);
+
+/// 进程级的关闭协调：收到信号后停止接收新工作，等待下载任务在期限内完成
+#[derive(Default)]
+pub struct Shutdown {
+    /// 开始关闭：停止接收更新与请求，排队中的任务不再启动
+    begin: CancellationToken,
+    /// 等待期限已到：仍在运行的任务中断并保留任务日志，重启后继续
+    deadline: CancellationToken,
+    running: AtomicUsize,
+    idle: Notify,
+}
+
+/// 运行中的任务，离开作用域时计数减一
+pub struct TaskGuard(());
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if SHUTDOWN.running.fetch_sub(1, Ordering::SeqCst) == 1 {
+            SHUTDOWN.idle.notify_waiters();
+        }
+    }
+}
+
+/// 登记一个需要在关闭前等待的任务
+pub fn track() -> TaskGuard {
+    SHUTDOWN.running.fetch_add(1, Ordering::SeqCst);
+    TaskGuard(())
+}
+
+pub fn running() -> usize {
+    SHUTDOWN.running.load(Ordering::SeqCst)
+}
+
+/// 开始关闭的信号
+pub fn token() -> &'static CancellationToken {
+    &SHUTDOWN.begin
+}
+
+/// 等待期限已到的信号
+pub fn deadline() -> &'static CancellationToken {
+    &SHUTDOWN.deadline
+}
+
+pub fn trigger() {
+    if !SHUTDOWN.begin.is_cancelled() {
+        info!("开始关闭服务");
+        SHUTDOWN.begin.cancel();
+    }
+}
+
+/// 监听 ctrl-c 与 SIGTERM，收到后触发关闭
+pub fn listen_signals() {
+    tokio::spawn(async {
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(e) => {
+                    warn!(error = %e, "无法监听 SIGTERM");
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => info!("收到 ctrl-c"),
+            _ = terminate => info!("收到 SIGTERM"),
+        }
+        trigger();
+    });
+}
+
+async fn wait_idle() {
+    loop {
+        let notified = SHUTDOWN.idle.notified();
+        if running() == 0 {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// 等待运行中的任务完成；超过期限则通知任务中断，返回是否全部按时完成
+pub async fn drain(timeout: Duration) -> bool {
+    if tokio::time::timeout(timeout, wait_idle()).await.is_ok() {
+        return true;
+    }
+    warn!(running = running(), "关闭等待超时，中断剩余任务");
+    SHUTDOWN.deadline.cancel();
+    // 中断后任务只需更新状态消息，给出短暂的收尾时间
+    let _ = tokio::time::timeout(Duration::from_secs(5), wait_idle()).await;
+    false
+}
@@ -1,54 +1,116 @@
 use crate::config::Config;
+use crate::error::BotError;
+use crate::models::ArchiveStream;
+use crate::services::links::{self, SignedLink};
+use crate::services::{api, health, jobs, opds, proxy, reader, webhook};
 use crate::utils::cache;
+use crate::utils::http;
+use crate::utils::metrics;
+use crate::utils::zip::ZipStream;
 use actix_files::NamedFile;
+use actix_web::dev::ServerHandle;
 use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
 use actix_web::{App, HttpRequest, HttpResponse, HttpServer, middleware::Logger, web};
+use futures::StreamExt;
 use mime_guess::MimeGuess;
 use serde::Deserialize;
 use tokio::fs;
 use tracing::{error, info};
 
+/// 下载参数：在线打包的令牌 `token`，或签名链接的 `path`/`exp`/`max`/`sig`
 #[derive(Deserialize)]
 struct DownloadQuery {
-    token: String,
+    token: Option<String>,
+    path: Option<String>,
+    exp: Option<i64>,
+    max: Option<u32>,
+    sig: Option<String>,
 }
 
 async fn handle_download(
     req: HttpRequest,
     query: web::Query<DownloadQuery>,
 ) -> actix_web::Result<HttpResponse> {
-    let token_str = query.token.trim();
+    if let Some(sig) = &query.sig {
+        let (Some(path), Some(exp)) = (&query.path, query.exp) else {
+            return Ok(HttpResponse::BadRequest().body("invalid link"));
+        };
+        let link =
+            SignedLink { path: path.clone(), exp, max: query.max.unwrap_or(0), sig: sig.clone() };
+        return handle_signed(req, link).await;
+    }
+
+    let token_str = query.token.as_deref().unwrap_or_default().trim();
     if uuid::Uuid::parse_str(token_str).is_err() {
         error!(token = token_str, "invalid token format");
         return Ok(HttpResponse::BadRequest().body("invalid token"));
     }
 
-    let cache = cache::download_token_cache();
-    let path_opt = cache.get(token_str).await;
-    if path_opt.is_none() {
+    let Some(target) = cache::lookup(cache::stream_token_cache(), token_str).await else {
         error!(token = token_str, "token not found in cache");
         return Ok(HttpResponse::NotFound().finish());
+    };
+    let concurrency =
+        req.app_data::<web::Data<Config>>().map(|d| d.server.download_concurrency).unwrap_or(1);
+    info!(token = token_str, title = %target.title, "streaming archive");
+    Ok(stream_archive(target, concurrency))
+}
+
+/// 签名链接：校验签名与有效期，路径相对下载目录
+async fn handle_signed(req: HttpRequest, link: SignedLink) -> actix_web::Result<HttpResponse> {
+    if !links::store().verify(&link) {
+        error!(path = %link.path, "invalid link signature");
+        return Ok(HttpResponse::Forbidden().body("invalid signature"));
+    }
+    if link.is_expired(chrono::Utc::now().timestamp()) {
+        info!(path = %link.path, "link expired");
+        return Ok(HttpResponse::Gone().body("link expired"));
     }
-    let path = path_opt.unwrap();
 
-    let base_path_string = req
-        .app_data::<web::Data<Config>>()
+    let path = std::path::Path::new(&download_path(&req)).join(&link.path);
+    serve_file(&req, &path.to_string_lossy(), &link).await
+}
+
+fn download_path(req: &HttpRequest) -> String {
+    req.app_data::<web::Data<Config>>()
         .map(|d| d.server.download_path.clone())
-        .unwrap_or_else(|| "/tmp/mangabot/downloads".to_string());
+        .unwrap_or_else(|| "/tmp/mangabot/downloads".to_string())
+}
+
+/// 发送下载目录中的文件；限次的签名链接在确认文件可下载后计数
+async fn serve_file(
+    req: &HttpRequest,
+    path: &str,
+    link: &SignedLink,
+) -> actix_web::Result<HttpResponse> {
+    let base_path_string = download_path(req);
     let base = std::path::Path::new(&base_path_string);
 
-    let target = std::path::Path::new(&path);
+    let target = std::path::Path::new(path);
     if !crate::utils::fs::canonicalize_within(base, target) {
         error!(path = %path, "file path out of download directory");
         return Ok(HttpResponse::NotFound().finish());
     }
 
-    if fs::metadata(&path).await.is_err() {
+    if fs::metadata(path).await.is_err() {
         error!(path = %path, "file not found");
         return Ok(HttpResponse::NotFound().finish());
     }
 
-    let file = match NamedFile::open_async(&path).await {
+    match links::store().record(link).await {
+        Ok(true) => {}
+        Ok(false) => {
+            info!(path = %path, max = link.max, "download limit reached");
+            return Ok(HttpResponse::Gone().body("download limit reached"));
+        }
+        Err(e) => {
+            error!(path = %path, error = %e, "download count error");
+            return Ok(HttpResponse::InternalServerError().finish());
+        }
+    }
+
+    let file = match NamedFile::open_async(path).await {
         Ok(f) => f,
         Err(e) => {
             error!(path = %path, error = %e, "file open error");
@@ -56,40 +118,149 @@ async fn handle_download(
         }
     };
 
-    let filename = std::path::Path::new(&path)
-        .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("download");
+    let filename =
+        std::path::Path::new(path).file_name().and_then(|s| s.to_str()).unwrap_or("download");
 
     let cd = ContentDisposition {
         disposition: DispositionType::Attachment,
         parameters: vec![DispositionParam::Filename(filename.to_string())],
     };
 
-    let mime = MimeGuess::from_path(&path).first_or_octet_stream();
+    let mime = MimeGuess::from_path(path).first_or_octet_stream();
     let file = file.set_content_type(mime).set_content_disposition(cd);
-    Ok(file.into_response(&req))
+    Ok(file.into_response(req))
+}
+
+/// Prometheus 指标；任务数、缓存条目与下载目录占用在抓取时刷新
+async fn handle_metrics(req: HttpRequest) -> HttpResponse {
+    let m = metrics::metrics();
+    if let Some(store) = jobs::try_store() {
+        m.jobs.reset();
+        for job in store.list().await {
+            m.jobs.with_label_values(&[job.status.as_str()]).inc();
+        }
+    }
+    for (name, count) in cache::entry_counts() {
+        m.cache_entries.with_label_values(&[name]).set(count as i64);
+    }
+    let dir = download_path(&req);
+    let bytes = web::block(move || {
+        walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum::<u64>()
+    })
+    .await
+    .unwrap_or(0);
+    m.disk_usage.set(bytes as i64);
+
+    HttpResponse::Ok().content_type("text/plain; version=0.0.4; charset=utf-8").body(m.render())
+}
+
+/// 在线打包：按页序抓取页面并直接输出 zip，不经过磁盘；
+/// 抓取失败的页面以同名的 .missing.txt 占位，记录原始地址与错误
+pub(crate) fn stream_archive(target: ArchiveStream, concurrency: usize) -> HttpResponse {
+    let filename = format!("{}.zip", target.title);
+    let total = target.images.len();
+    let body = async_stream::stream! {
+        let mut zip = ZipStream::new();
+        // 预取后续页面，但仍按页序输出
+        let mut pages = futures::stream::iter(target.images.into_iter().enumerate())
+            .map(|(index, url)| async move {
+                let result = http::fetch_page(&url).await;
+                (index, url, result)
+            })
+            .buffered(concurrency.max(1));
+        while let Some((index, url, result)) = pages.next().await {
+            let (name, bytes) = match result {
+                Ok(bytes) => {
+                    let ext = http::page_extension(&bytes, None, &url);
+                    (format!("{}.{}", http::page_name(index, total), ext), bytes)
+                }
+                Err(e) => {
+                    error!(url = %url, error = %e, "streaming page failed");
+                    let note = format!("第 {} 页下载失败\n{}\n{}\n", index + 1, url, e);
+                    (format!("{}.missing.txt", http::page_name(index, total)), note.into_bytes())
+                }
+            };
+            let entry = zip.start_entry(&name).and_then(|header| {
+                zip.update(&bytes)?;
+                Ok((header, zip.finish_entry()?))
+            });
+            let (header, descriptor) = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error!(error = %e, "streaming archive aborted");
+                    yield Err(actix_web::error::ErrorInternalServerError(e));
+                    return;
+                }
+            };
+            yield Ok::<_, actix_web::Error>(Bytes::from(header));
+            yield Ok(Bytes::from(bytes));
+            yield Ok(Bytes::from(descriptor));
+        }
+        match zip.finish() {
+            Ok(directory) => yield Ok(Bytes::from(directory)),
+            Err(e) => {
+                error!(error = %e, "streaming archive aborted");
+                yield Err(actix_web::error::ErrorInternalServerError(e));
+            }
+        }
+    };
+
+    let cd = ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(filename)],
+    };
+    HttpResponse::Ok().content_type("application/zip").insert_header(cd).streaming(body)
 }
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.route("/download", web::get().to(handle_download));
+    cfg.route("/download", web::get().to(handle_download))
+        .route("/read/{aid}", web::get().to(reader::handle_read))
+        .route("/img", web::get().to(proxy::handle_image))
+        .route("/metrics", web::get().to(handle_metrics))
+        .configure(health::configure)
+        .configure(webhook::configure)
+        .configure(api::configure)
+        .configure(opds::configure);
 }
 
-pub fn start(config: Config) -> crate::error::Result<()> {
+/// 在独立线程中启动 web 服务；端口绑定失败时作为启动错误返回
+pub fn start(config: Config) -> crate::error::Result<ServerHandle> {
     let addr = ("0.0.0.0", config.server.port);
+    let shutdown_timeout = config.server.shutdown_timeout;
     info!(port = config.server.port, "starting web server");
+    let (tx, rx) = std::sync::mpsc::channel();
     std::thread::spawn(move || {
         let sys = actix_web::rt::System::new();
         let data = web::Data::new(config);
-        let _ = sys.block_on(async move {
-            let _ = HttpServer::new(move || {
+        sys.block_on(async move {
+            let server = HttpServer::new(move || {
                 App::new().wrap(Logger::default()).app_data(data.clone()).configure(configure)
             })
-            .bind(addr)
-            .expect("bind failed")
-            .run()
-            .await;
+            // 关闭由 shutdown 模块统一协调
+            .disable_signals()
+            .shutdown_timeout(shutdown_timeout)
+            .bind(addr);
+            let server = match server {
+                Ok(server) => server.run(),
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = tx.send(Ok(server.handle()));
+            if let Err(e) = server.await {
+                error!(error = %e, "web server stopped");
+            }
         });
     });
-    Ok(())
+    let handle = rx
+        .recv()
+        .map_err(|_| BotError::InternalError("web server thread exited".to_string()))??;
+    Ok(handle)
 }
@@ -0,0 +1,117 @@
+use crate::config::Config;
+use crate::error::{BotError, Result};
+use crate::utils::constant_time_eq;
+use actix_web::{HttpRequest, HttpResponse, web};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use teloxide::Bot;
+use teloxide::payloads::SetWebhookSetters;
+use teloxide::requests::Requester;
+use teloxide::stop::{StopFlag, StopToken, mk_stop_token};
+use teloxide::types::Update;
+use teloxide::update_listeners::{StatefulListener, UpdateListener};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Telegram 回调时携带的密钥请求头
+pub const SECRET_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+/// 接收更新的路由，反向代理需将 `webhook_url` 转发到此路径
+pub const WEBHOOK_PATH: &str = "/webhook";
+
+static WEBHOOK: OnceLock<Webhook> = OnceLock::new();
+
+/// 接收更新的方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateMode {
+    /// 主动轮询 getUpdates
+    #[default]
+    Polling,
+    /// 由 Telegram 推送到 web 服务
+    Webhook,
+}
+
+struct Webhook {
+    tx: mpsc::UnboundedSender<Update>,
+    flag: StopFlag,
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = std::result::Result<Update, Infallible>> + Send>>;
+
+fn updates(state: &mut (UpdateStream, StopToken)) -> &mut UpdateStream {
+    &mut state.0
+}
+
+/// 创建由 web 路由投递更新的监听器，只能调用一次
+pub fn listener() -> Result<impl UpdateListener<Err = Infallible>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (stop_token, flag) = mk_stop_token();
+    WEBHOOK
+        .set(Webhook { tx, flag: flag.clone() })
+        .map_err(|_| BotError::InternalError("WEBHOOK init failed".to_string()))?;
+
+    let stream: UpdateStream = Box::pin(
+        futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|update| (Ok(update), rx))
+        })
+        .take_until(flag),
+    );
+    Ok(StatefulListener::new((stream, stop_token), updates, |state: &mut (_, StopToken)| {
+        state.1.clone()
+    }))
+}
+
+/// 校验用的密钥；未配置时由 Bot Token 派生，重启后保持不变
+pub fn secret(config: &Config) -> String {
+    if !config.bot.webhook_secret.is_empty() {
+        return config.bot.webhook_secret.clone();
+    }
+    Sha256::digest(config.bot.telegram_token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 向 Telegram 注册 webhook 地址与密钥
+pub async fn register(bot: &Bot, config: &Config) -> Result<()> {
+    let url = reqwest::Url::parse(&config.bot.webhook_url).map_err(|e| {
+        config::ConfigError::Message(format!("webhook_url 无效 `{}`: {e}", config.bot.webhook_url))
+    })?;
+    bot.set_webhook(url.clone()).secret_token(secret(config)).await?;
+    info!(url = %url, "webhook 已注册");
+    Ok(())
+}
+
+/// 接收 Telegram 推送的更新，密钥不符返回 401，未启用 webhook 模式时返回 503
+pub async fn handle_update(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+    let Some(config) = req.app_data::<web::Data<Config>>() else {
+        return HttpResponse::InternalServerError().finish();
+    };
+    let header = req.headers().get(SECRET_HEADER).map(|v| v.as_bytes()).unwrap_or_default();
+    if !constant_time_eq(header, secret(config).as_bytes()) {
+        warn!("webhook 密钥校验失败");
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    let Some(webhook) = WEBHOOK.get().filter(|w| !w.flag.is_stopped()) else {
+        return HttpResponse::ServiceUnavailable().finish();
+    };
+    match serde_json::from_slice::<Update>(&body) {
+        Ok(update) => {
+            if webhook.tx.send(update).is_err() {
+                return HttpResponse::ServiceUnavailable().finish();
+            }
+        }
+        // 无法解析的更新直接丢弃，避免 Telegram 反复重试
+        Err(e) => error!(error = %e, "webhook 更新解析失败"),
+    }
+    HttpResponse::Ok().finish()
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route(WEBHOOK_PATH, web::post().to(handle_update));
+}
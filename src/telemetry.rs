@@ -1,14 +1,14 @@
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
+use std::sync::Mutex;
 use tracing::info;
 use tracing_appender::non_blocking;
 use tracing_appender::rolling;
 use tracing_subscriber::fmt::writer::MakeWriterExt;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
-static GUARD: OnceLock<non_blocking::WorkerGuard> = OnceLock::new();
+static GUARD: Mutex<Option<non_blocking::WorkerGuard>> = Mutex::new(None);
 
 fn split_path(path: &str) -> (PathBuf, String) {
     let p = Path::new(path);
@@ -23,7 +23,7 @@ pub fn init_telemetry(config: &crate::config::Config) -> crate::error::Result<()
 
     let appender = rolling::daily(&dir, &file);
     let (non_blocking_appender, guard) = non_blocking(appender);
-    let _ = GUARD.set(guard);
+    *GUARD.lock().unwrap() = Some(guard);
 
     let stdout = io::stdout.with_max_level(tracing::Level::TRACE);
     let fmt_layer = fmt::layer()
@@ -39,3 +39,10 @@ pub fn init_telemetry(config: &crate::config::Config) -> crate::error::Result<()
     info!("Telemetry initialized");
     Ok(())
 }
+
+/// 退出前写出日志文件中尚未落盘的记录，之后只输出到标准输出
+pub fn flush() {
+    if let Some(guard) = GUARD.lock().unwrap().take() {
+        drop(guard);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::{BotError, Result};
+use crate::models::MangaDetail;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// 作品打包格式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    Cbz,
+    Epub,
+    Pdf,
+}
+
+impl ArchiveFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Some(Self::Zip),
+            "cbz" => Some(Self::Cbz),
+            "epub" => Some(Self::Epub),
+            "pdf" => Some(Self::Pdf),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::Cbz => "cbz",
+            Self::Epub => "epub",
+            Self::Pdf => "pdf",
+        }
+    }
+
+    /// 完整打包 `pages` 页后压缩包内应有的文件数
+    pub fn expected_entries(&self, pages: usize) -> usize {
+        match self {
+            Self::Zip => pages,
+            Self::Cbz => pages + 1, // ComicInfo.xml
+            // mimetype、container.xml、content.opf、nav.xhtml、封面，以及每页的图片和 XHTML
+            Self::Epub => 5 + pages * 2,
+            Self::Pdf => pages, // 页数
+        }
+    }
+}
+
+/// 打包所需的作品信息
+pub struct ArchiveSource<'a> {
+    pub dir: &'a str,
+    /// 按阅读顺序排列的页面文件
+    pub pages: &'a [String],
+    pub detail: &'a MangaDetail,
+    pub source_url: &'a str,
+    /// 封面图片内容（EPUB 使用），缺省时取第一页
+    pub cover: Option<&'a [u8]>,
+}
+
+/// 按格式打包作品（阻塞操作，需在 spawn_blocking 中调用）
+pub fn build(format: ArchiveFormat, src: &ArchiveSource, out_path: &str) -> Result<()> {
+    if src.pages.is_empty() {
+        return Err(BotError::InternalError(format!("no pages to archive: {}", src.dir)));
+    }
+
+    match format {
+        // 只打包本次（或本分卷）的页面
+        ArchiveFormat::Zip => super::zip::compress_files(src.pages, out_path),
+        ArchiveFormat::Cbz => {
+            let comic_info = comic_info_xml(src.detail, src.pages.len(), src.source_url);
+            super::zip::compress_pages(
+                src.pages,
+                &[("ComicInfo.xml", comic_info.into_bytes())],
+                out_path,
+            )
+        }
+        ArchiveFormat::Epub => super::epub::write_epub(src, out_path),
+        ArchiveFormat::Pdf => super::pdf::write_pdf(src, out_path),
+    }
+}
+
+/// 按页面大小把作品顺序切成若干卷，每卷总大小不超过 `budget`（单页超限时独占一卷）
+pub fn plan_volumes(sizes: &[u64], budget: u64) -> Vec<Range<usize>> {
+    let mut volumes = Vec::new();
+    let (mut start, mut total) = (0, 0u64);
+    for (i, size) in sizes.iter().enumerate() {
+        if i > start && total + size > budget {
+            volumes.push(start..i);
+            (start, total) = (i, 0);
+        }
+        total += size;
+    }
+    if start < sizes.len() {
+        volumes.push(start..sizes.len());
+    }
+    volumes
+}
+
+/// 校验已有的打包文件是否完整，可直接复用（阻塞操作）
+pub fn verify(format: ArchiveFormat, path: &str, pages: usize) -> bool {
+    let expected = format.expected_entries(pages);
+    match format {
+        ArchiveFormat::Pdf => super::pdf::verify_pdf(path, expected),
+        _ => super::zip::verify_archive(path, expected),
+    }
+}
+
+/// 生成 Komga/Kavita 等阅读器识别的 ComicInfo.xml
+pub fn comic_info_xml(detail: &MangaDetail, page_count: usize, source_url: &str) -> String {
+    use super::escape_xml;
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+    let mut field = |name: &str, value: &str| {
+        if !value.is_empty() {
+            xml.push_str(&format!("  <{name}>{}</{name}>\n", escape_xml(value)));
+        }
+    };
+
+    field("Title", &detail.title);
+    field("Series", &detail.title);
+    field("Summary", &detail.description);
+    field("Writer", &detail.author);
+    field("Genre", &detail.category);
+    field("Tags", &detail.tags.join(","));
+    field("Web", source_url);
+    field("PageCount", &page_count.to_string());
+    field("Manga", "Yes");
+
+    xml.push_str("  <Pages>\n");
+    for i in 0..page_count {
+        if i == 0 {
+            xml.push_str("    <Page Image=\"0\" Type=\"FrontCover\" />\n");
+        } else {
+            xml.push_str(&format!("    <Page Image=\"{}\" />\n", i));
+        }
+    }
+    xml.push_str("  </Pages>\n</ComicInfo>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::plan_volumes;
+
+    #[test]
+    fn test_plan_volumes() {
+        assert_eq!(plan_volumes(&[3, 3, 3, 3], 10), vec![0..3, 3..4]);
+        assert_eq!(plan_volumes(&[3, 3, 3, 3], 12), vec![0..4]);
+        // 超过预算的单页独占一卷
+        assert_eq!(plan_volumes(&[2, 20, 2], 10), vec![0..1, 1..2, 2..3]);
+        assert!(plan_volumes(&[], 10).is_empty());
+    }
+}
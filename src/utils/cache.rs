@@ -1,6 +1,9 @@
 use crate::config::Config;
-use crate::models::MangaDetail;
+use crate::models::{ArchiveStream, MangaDetail};
+use crate::utils::metrics;
 use moka::future::Cache;
+use std::borrow::Borrow;
+use std::hash::Hash;
 use std::sync::OnceLock;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
@@ -8,55 +11,82 @@ use tracing::info;
 
 static IMAGE_CACHE: OnceLock<Cache<String, Vec<String>>> = OnceLock::new();
 static INFO_CACHE: OnceLock<Cache<String, MangaDetail>> = OnceLock::new();
-static DOWNLOAD_TOKEN_CACHE: OnceLock<Cache<String, String>> = OnceLock::new();
+static STREAM_TOKEN_CACHE: OnceLock<Cache<String, ArchiveStream>> = OnceLock::new();
 
 static SEARCH_KEY_NUM_CACHE: OnceLock<Cache<String, u64>> = OnceLock::new();
 static SEARCH_NUM_KEY_CACHE: OnceLock<Cache<u64, String>> = OnceLock::new();
+/// 批量下载按钮对应的作品列表（回调数据长度有限，只携带编号）
+static BATCH_CACHE: OnceLock<Cache<u64, Vec<i64>>> = OnceLock::new();
+/// 重试按钮对应的 /zip 参数（aid、格式、转码、页码区间）
+static RETRY_CACHE: OnceLock<Cache<u64, Vec<String>>> = OnceLock::new();
 
 static COUNTER: OnceLock<AtomicU64> = OnceLock::new();
 static MAX_SEARCH_KEY_NUM: OnceLock<u64> = OnceLock::new();
 
 pub fn init(config: &Config) -> crate::error::Result<()> {
-    fn build_cache<K, V>(ttl_minutes: u64, max_capacity: u64) -> Cache<K, V>
+    fn build_cache<K, V>(name: &'static str, ttl_minutes: u64, max_capacity: u64) -> Cache<K, V>
     where
         K: std::hash::Hash + Eq + Send + Sync + std::fmt::Debug + 'static,
         V: Send + Sync + std::fmt::Debug + Clone + 'static,
     {
         Cache::builder()
+            .name(name)
             .max_capacity(max_capacity)
             .time_to_live(Duration::from_mins(ttl_minutes)) // 修复2：from_secs 替代 from_mins
-            .eviction_listener(|key, _value, cause| {
+            .eviction_listener(move |key, _value, cause| {
                 tracing::info!(?key, ?cause, "缓存项被驱逐");
+                let cause = format!("{:?}", cause).to_ascii_lowercase();
+                metrics::metrics().cache_evictions.with_label_values(&[name, &cause]).inc();
             })
             .build()
     }
 
-    let image_cache: Cache<String, Vec<String>> =
-        build_cache(config.manga.cache_image_minute_ttl, config.manga.cache_image_max_size);
+    let image_cache: Cache<String, Vec<String>> = build_cache(
+        "image",
+        config.manga.cache_image_minute_ttl,
+        config.manga.cache_image_max_size,
+    );
 
     let info_cache: Cache<String, MangaDetail> =
-        build_cache(config.manga.cache_info_minute_ttl, config.manga.cache_info_max_size);
+        build_cache("info", config.manga.cache_info_minute_ttl, config.manga.cache_info_max_size);
 
-    let download_token_client: Cache<String, String> = build_cache(
-        config.server.cache_download_token_minute_ttl,
-        config.server.cache_download_token_max_size,
+    let stream_token_cache: Cache<String, ArchiveStream> = build_cache(
+        "stream_token",
+        config.server.cache_stream_token_minute_ttl,
+        config.server.cache_stream_token_max_size,
     );
 
     let search_key_num_cache: Cache<String, u64> = build_cache(
+        "search_key_num",
         config.server.cache_search_key_num_minute_ttl,
         config.server.cache_search_key_num_max_size,
     );
 
     let search_num_key_cache: Cache<u64, String> = build_cache(
+        "search_num_key",
         config.server.cache_search_key_num_minute_ttl,
         config.server.cache_search_key_num_max_size,
     );
 
+    let batch_cache: Cache<u64, Vec<i64>> = build_cache(
+        "batch",
+        config.server.cache_button_args_minute_ttl,
+        config.server.cache_button_args_max_size,
+    );
+
+    let retry_cache: Cache<u64, Vec<String>> = build_cache(
+        "retry",
+        config.server.cache_button_args_minute_ttl,
+        config.server.cache_button_args_max_size,
+    );
+
     IMAGE_CACHE.set(image_cache).expect("IMAGE_CACHE init failed");
     INFO_CACHE.set(info_cache).expect("INFO_CACHE init failed");
-    DOWNLOAD_TOKEN_CACHE.set(download_token_client).expect("DOWNLOAD_TOKEN_CACHE init failed");
+    STREAM_TOKEN_CACHE.set(stream_token_cache).expect("STREAM_TOKEN_CACHE init failed");
     SEARCH_KEY_NUM_CACHE.set(search_key_num_cache).expect("SEARCH_KEY_NUM_CACHE init failed");
     SEARCH_NUM_KEY_CACHE.set(search_num_key_cache).expect("SEARCH_NUM_KEY_CACHE init failed");
+    BATCH_CACHE.set(batch_cache).expect("BATCH_CACHE init failed");
+    RETRY_CACHE.set(retry_cache).expect("RETRY_CACHE init failed");
     COUNTER.set(AtomicU64::new(0)).expect("COUNTER init failed");
     MAX_SEARCH_KEY_NUM
         .set(config.server.cache_search_key_num_max_size)
@@ -73,8 +103,51 @@ pub fn info_cache() -> &'static Cache<String, MangaDetail> {
     INFO_CACHE.get().expect("INFO_CACHE not initialized")
 }
 
-pub fn download_token_cache() -> &'static Cache<String, String> {
-    DOWNLOAD_TOKEN_CACHE.get().expect("DOWNLOAD_TOKEN_CACHE not initialized")
+pub fn stream_token_cache() -> &'static Cache<String, ArchiveStream> {
+    STREAM_TOKEN_CACHE.get().expect("STREAM_TOKEN_CACHE not initialized")
+}
+
+/// 所有缓存是否已初始化
+pub fn is_initialized() -> bool {
+    IMAGE_CACHE.get().is_some()
+        && INFO_CACHE.get().is_some()
+        && STREAM_TOKEN_CACHE.get().is_some()
+        && SEARCH_KEY_NUM_CACHE.get().is_some()
+        && SEARCH_NUM_KEY_CACHE.get().is_some()
+        && BATCH_CACHE.get().is_some()
+        && RETRY_CACHE.get().is_some()
+}
+
+/// 查询缓存并按缓存名记录命中与未命中
+pub async fn lookup<K, Q, V>(cache: &Cache<K, V>, key: &Q) -> Option<V>
+where
+    K: Borrow<Q> + Hash + Eq + Send + Sync + 'static,
+    Q: Hash + Eq + ?Sized,
+    V: Clone + Send + Sync + 'static,
+{
+    let value = cache.get(key).await;
+    metrics::record_cache_lookup(cache.name().unwrap_or("unnamed"), value.is_some());
+    value
+}
+
+/// 各缓存当前的条目数（近似值）
+pub fn entry_counts() -> Vec<(&'static str, u64)> {
+    fn count<K, V>(cache: &OnceLock<Cache<K, V>>) -> u64
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        cache.get().map_or(0, |c| c.entry_count())
+    }
+    vec![
+        ("image", count(&IMAGE_CACHE)),
+        ("info", count(&INFO_CACHE)),
+        ("stream_token", count(&STREAM_TOKEN_CACHE)),
+        ("search_key_num", count(&SEARCH_KEY_NUM_CACHE)),
+        ("search_num_key", count(&SEARCH_NUM_KEY_CACHE)),
+        ("batch", count(&BATCH_CACHE)),
+        ("retry", count(&RETRY_CACHE)),
+    ]
 }
 
 fn increment_cyclic() -> u64 {
@@ -98,7 +171,7 @@ pub async fn search_key_to_num(key: &str) -> u64 {
         return 0;
     }
     let key_cache = key_cache.unwrap();
-    if let Some(num) = key_cache.get(key).await {
+    if let Some(num) = lookup(key_cache, key).await {
         num
     } else {
         let num = increment_cyclic();
@@ -117,5 +190,33 @@ pub async fn search_num_to_key(num: u64) -> Option<String> {
         return None;
     }
     let num_cache = num_cache.unwrap();
-    num_cache.get(&num).await
+    lookup(num_cache, &num).await
+}
+
+/// 登记一组作品，返回可放进回调数据的编号
+pub async fn batch_to_num(aids: Vec<i64>) -> u64 {
+    let Some(cache) = BATCH_CACHE.get() else {
+        return 0;
+    };
+    let num = increment_cyclic();
+    cache.insert(num, aids).await;
+    num
+}
+
+pub async fn num_to_batch(num: u64) -> Option<Vec<i64>> {
+    lookup(BATCH_CACHE.get()?, &num).await
+}
+
+/// 登记重试参数，返回可放进回调数据的编号；页码区间可能超出回调数据长度
+pub async fn retry_to_num(args: Vec<String>) -> u64 {
+    let Some(cache) = RETRY_CACHE.get() else {
+        return 0;
+    };
+    let num = increment_cyclic();
+    cache.insert(num, args).await;
+    num
+}
+
+pub async fn num_to_retry(num: u64) -> Option<Vec<String>> {
+    lookup(RETRY_CACHE.get()?, &num).await
 }
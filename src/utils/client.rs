@@ -1,14 +1,23 @@
 use crate::config::Config;
+use crate::utils::throttle::RateLimiter;
 use reqwest::{
     Client, ClientBuilder,
     header::{HeaderMap, HeaderValue, REFERER, USER_AGENT},
 };
-use std::sync::OnceLock;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
 static HTTP_USER_AGENT: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 18_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.5 Mobile/15E148 Safari/604.1";
 static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
 static DOWNLOAD_CLIENT: OnceLock<Client> = OnceLock::new();
+/// 全局图片请求并发上限，所有下载任务共享
+static FETCH_LIMIT: OnceLock<Semaphore> = OnceLock::new();
+/// 全局下载带宽，所有任务共享
+static BANDWIDTH: OnceLock<RateLimiter> = OnceLock::new();
+/// 单个任务的下载带宽，各任务按同一速率各自限速
+static JOB_BANDWIDTH: OnceLock<Arc<AtomicU64>> = OnceLock::new();
 
 pub fn init(config: &Config) -> crate::error::Result<()> {
     if HTTP_CLIENT.get().is_some() || DOWNLOAD_CLIENT.get().is_some() {
@@ -42,6 +51,15 @@ pub fn init(config: &Config) -> crate::error::Result<()> {
     DOWNLOAD_CLIENT
         .set(download_client)
         .expect("DOWNLOAD_CLIENT already set (this should be unreachable)");
+    FETCH_LIMIT
+        .set(Semaphore::new(config.server.max_concurrent_fetches.max(1)))
+        .expect("FETCH_LIMIT already set (this should be unreachable)");
+    BANDWIDTH
+        .set(RateLimiter::with_rate(config.server.bandwidth_limit))
+        .expect("BANDWIDTH already set (this should be unreachable)");
+    JOB_BANDWIDTH
+        .set(Arc::new(AtomicU64::new(config.server.job_bandwidth_limit)))
+        .expect("JOB_BANDWIDTH already set (this should be unreachable)");
 
     Ok(())
 }
@@ -52,3 +70,33 @@ pub fn http() -> &'static Client {
 pub fn download() -> &'static Client {
     DOWNLOAD_CLIENT.get().expect("DOWNLOAD client not initialized — call `init()` first!")
 }
+
+/// 获取一个图片请求名额，持有期间计入全局并发
+pub async fn fetch_permit() -> SemaphorePermit<'static> {
+    FETCH_LIMIT
+        .get()
+        .expect("FETCH_LIMIT not initialized — call `init()` first!")
+        .acquire()
+        .await
+        .expect("FETCH_LIMIT closed")
+}
+
+/// 全局带宽限速器
+pub fn bandwidth() -> &'static RateLimiter {
+    BANDWIDTH.get().expect("BANDWIDTH not initialized — call `init()` first!")
+}
+
+/// 为一个任务创建带宽限速器，速率随 `set_job_bandwidth` 调整
+pub fn job_bandwidth() -> RateLimiter {
+    RateLimiter::new(Arc::clone(
+        JOB_BANDWIDTH.get().expect("JOB_BANDWIDTH not initialized — call `init()` first!"),
+    ))
+}
+
+/// 运行时调整单任务带宽（字节/秒，0 表示不限速），对进行中的任务立即生效
+pub fn set_job_bandwidth(bytes_per_sec: u64) {
+    JOB_BANDWIDTH
+        .get()
+        .expect("JOB_BANDWIDTH not initialized — call `init()` first!")
+        .store(bytes_per_sec, std::sync::atomic::Ordering::Relaxed);
+}
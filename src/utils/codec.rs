@@ -1,4 +1,4 @@
-use crate::bot::commands::Command;
+use crate::bot::commands::{Command, ZipArgs};
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -165,8 +165,45 @@ pub async fn decode_command(
             Command::Preview(aid.map(|s| s.to_string()), page)
         }
         "zip" => {
+            let aid = if parts.len() > 1 { parts[1].parse::<i64>().ok() } else { None };
+            let format = if parts.len() > 2 { Some(parts[2].to_string()) } else { None };
+            let transcode = if parts.len() > 3 { Some(parts[3].to_string()) } else { None };
+            let pages = if parts.len() > 4 { Some(parts[4].to_string()) } else { None };
+            Command::Zip(ZipArgs {
+                aids: aid.into_iter().collect(),
+                pages,
+                format,
+                transcode,
+                combine: false,
+            })
+        }
+        "zipall" => {
+            let num = if parts.len() > 1 { parts[1].parse::<u64>().unwrap_or(0) } else { 0 };
+            let combine = parts.len() > 2 && parts[2] == "merge";
+            let transcode = if parts.len() > 3 { Some(parts[3].to_string()) } else { None };
+            let aids = super::cache::num_to_batch(num).await.unwrap_or_default();
+            Command::Zip(ZipArgs { aids, pages: None, format: None, transcode, combine })
+        }
+        "rezip" => {
+            let num = if parts.len() > 1 { parts[1].parse::<u64>().unwrap_or(0) } else { 0 };
+            let mut args = super::cache::num_to_retry(num).await.unwrap_or_default().into_iter();
+            let aid = args.next().and_then(|a| a.parse::<i64>().ok());
+            Command::Zip(ZipArgs {
+                aids: aid.into_iter().collect(),
+                format: args.next(),
+                transcode: args.next(),
+                pages: args.next(),
+                combine: false,
+            })
+        }
+        "stream" => {
             let aid = if parts.len() > 1 { parts[1].parse::<i64>().unwrap_or(0) } else { 0 };
-            Command::Zip(aid)
+            Command::Stream(aid)
+        }
+        "jobs" => Command::Jobs,
+        "cancel" => {
+            let job_id = if parts.len() > 1 { parts[1].to_string() } else { String::new() };
+            Command::Cancel(job_id)
         }
         "cate" => {
             let cate = if parts.len() > 1 { Some(parts[1].to_string()) } else { None };
@@ -249,13 +286,15 @@ impl CommandArgsExt for Vec<CommandArg> {
     }
 }
 
+/// 回调数据超过 Telegram 的 64 字节上限时返回错误
 pub fn encode_command_button(
     text: &str,
     command: &str,
     args: &[impl Into<CommandArg> + Clone],
-) -> InlineKeyboardButton {
-    let data = encode_command(command, &args).unwrap();
-    InlineKeyboardButton::callback(text, data)
+) -> crate::error::Result<InlineKeyboardButton> {
+    let data = encode_command(command, &args)
+        .map_err(|e| crate::error::BotError::InvalidCommand { reason: e.to_string() })?;
+    Ok(InlineKeyboardButton::callback(text, data))
 }
 
 pub fn encode_command_link(
@@ -0,0 +1,196 @@
+use super::archive::ArchiveSource;
+use super::{escape_xml, img};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use zip::ZipWriter;
+use zip::write::FileOptions;
+
+/// 无法识别图片尺寸时使用的默认视口
+static DEFAULT_VIEWPORT: (u32, u32) = (1200, 1800);
+
+struct EpubPage {
+    image_name: String,
+    media_type: &'static str,
+    viewport: (u32, u32),
+}
+
+/// 写出固定版式（pre-paginated）的 EPUB 3：每张图片一个 XHTML 页面，
+/// 封面取 `src.cover`，缺省时使用第一页
+pub fn write_epub(src: &ArchiveSource, epub_path: &str) -> crate::error::Result<()> {
+    let file = File::create(epub_path)?;
+    let mut zip = ZipWriter::new(file);
+    let stored = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    // mimetype 必须是第一个且不压缩的条目
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    let width = src.pages.len().to_string().len().max(4);
+    let mut pages = Vec::with_capacity(src.pages.len());
+    for (i, page) in src.pages.iter().enumerate() {
+        let path = Path::new(page);
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("jpg").to_ascii_lowercase();
+        let image_name = format!("{:0width$}.{}", i + 1, ext);
+
+        zip.start_file(format!("OEBPS/images/{}", image_name), stored)?;
+        let mut f = File::open(path)?;
+        io::copy(&mut f, &mut zip)?;
+
+        pages.push(EpubPage {
+            media_type: img::mime_type(&ext),
+            viewport: img::dimensions(path).unwrap_or(DEFAULT_VIEWPORT),
+            image_name,
+        });
+    }
+
+    let cover_bytes = match src.cover {
+        Some(bytes) => bytes.to_vec(),
+        None => std::fs::read(&src.pages[0])?,
+    };
+    let cover_ext = img::sniff_extension(&cover_bytes).unwrap_or("jpg");
+    let cover_name = format!("cover.{}", cover_ext);
+    zip.start_file(format!("OEBPS/images/{}", cover_name), stored)?;
+    zip.write_all(&cover_bytes)?;
+
+    let title = escape_xml(&src.detail.title);
+    for (i, page) in pages.iter().enumerate() {
+        let (w, h) = page.viewport;
+        let xhtml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{title} - {page_no}</title>
+<meta name="viewport" content="width={w}, height={h}"/>
+<style>html, body {{ margin: 0; padding: 0; }} img {{ display: block; width: {w}px; height: {h}px; }}</style>
+</head>
+<body><img src="images/{image}" alt="{page_no}"/></body>
+</html>
+"#,
+            page_no = i + 1,
+            image = page.image_name,
+        );
+        zip.start_file(format!("OEBPS/{}", page_file_name(i, width)), deflated)?;
+        zip.write_all(xhtml.as_bytes())?;
+    }
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav_xhtml(&title, width).as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(
+        content_opf(src, &pages, &cover_name, img::mime_type(cover_ext), width).as_bytes(),
+    )?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn page_file_name(index: usize, width: usize) -> String {
+    format!("p{:0width$}.xhtml", index + 1)
+}
+
+static CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn nav_xhtml(title: &str, width: usize) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>{title}</title></head>
+<body>
+<nav epub:type="toc" id="toc"><ol><li><a href="{first}">{title}</a></li></ol></nav>
+</body>
+</html>
+"#,
+        first = page_file_name(0, width),
+    )
+}
+
+fn content_opf(
+    src: &ArchiveSource,
+    pages: &[EpubPage],
+    cover_name: &str,
+    cover_media_type: &str,
+    width: usize,
+) -> String {
+    let detail = src.detail;
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+
+    let mut metadata = vec![
+        format!("<dc:identifier id=\"bookid\">{}</dc:identifier>", escape_xml(src.source_url)),
+        format!("<dc:title>{}</dc:title>", escape_xml(&detail.title)),
+        "<dc:language>zh</dc:language>".to_string(),
+        format!("<meta property=\"dcterms:modified\">{}</meta>", modified),
+        "<meta property=\"rendition:layout\">pre-paginated</meta>".to_string(),
+        "<meta property=\"rendition:spread\">landscape</meta>".to_string(),
+        "<meta name=\"cover\" content=\"cover-image\"/>".to_string(),
+    ];
+    if !detail.author.is_empty() {
+        metadata.push(format!("<dc:creator>{}</dc:creator>", escape_xml(&detail.author)));
+    }
+    if !detail.description.is_empty() {
+        metadata
+            .push(format!("<dc:description>{}</dc:description>", escape_xml(&detail.description)));
+    }
+    if !detail.category.is_empty() {
+        metadata.push(format!("<dc:subject>{}</dc:subject>", escape_xml(&detail.category)));
+    }
+    for tag in &detail.tags {
+        metadata.push(format!("<dc:subject>{}</dc:subject>", escape_xml(tag)));
+    }
+
+    let mut manifest = vec![
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>"
+            .to_string(),
+        format!(
+            "<item id=\"cover-image\" href=\"images/{}\" media-type=\"{}\" properties=\"cover-image\"/>",
+            cover_name, cover_media_type
+        ),
+    ];
+    let mut spine = Vec::with_capacity(pages.len());
+    for (i, page) in pages.iter().enumerate() {
+        manifest.push(format!(
+            "<item id=\"img{n}\" href=\"images/{}\" media-type=\"{}\"/>",
+            page.image_name,
+            page.media_type,
+            n = i + 1
+        ));
+        manifest.push(format!(
+            "<item id=\"page{n}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+            page_file_name(i, width),
+            n = i + 1
+        ));
+        spine.push(format!("<itemref idref=\"page{}\"/>", i + 1));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="bookid" prefix="rendition: http://www.idpf.org/vocab/rendition/#">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    {}
+  </metadata>
+  <manifest>
+    {}
+  </manifest>
+  <spine>
+    {}
+  </spine>
+</package>
+"#,
+        metadata.join("\n    "),
+        manifest.join("\n    "),
+        spine.join("\n    ")
+    )
+}
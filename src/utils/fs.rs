@@ -15,6 +15,11 @@ pub fn sanitize_filename(name: &str) -> String {
     s.chars().take(128).collect()
 }
 
+/// 把文件的修改时间更新为当前时间；下载目录的保留策略以修改时间作为最近下载时间
+pub fn touch(path: &Path) -> std::io::Result<()> {
+    std::fs::File::options().write(true).open(path)?.set_modified(std::time::SystemTime::now())
+}
+
 pub fn canonicalize_within(base: &Path, target: &Path) -> bool {
     if let (Ok(base), Ok(target)) = (base.canonicalize(), target.canonicalize()) {
         target.starts_with(&base)
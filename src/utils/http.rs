@@ -1,11 +1,15 @@
 use crate::error::BotError;
 use crate::utils::client;
+use crate::utils::metrics;
+use crate::utils::throttle::RateLimiter;
 use futures::{StreamExt, stream};
 use reqwest::Url;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
 use tracing::{error, info};
 
 fn same_host(url: &str, base_url: &str) -> bool {
@@ -15,12 +19,38 @@ fn same_host(url: &str, base_url: &str) -> bool {
     }
 }
 
+/// 请求的目标主机是否允许：未配置 base_url 时不限制，否则须与 base_url 同主机，
+/// 或属于 `extra_hosts`（含其子域名）
+pub fn host_allowed(url: &str, base_url: &str, extra_hosts: &[String]) -> bool {
+    if base_url.is_empty() || same_host(url, base_url) {
+        return true;
+    }
+    let Ok(u) = Url::parse(url) else {
+        return false;
+    };
+    let Some(host) = u.host_str() else {
+        return false;
+    };
+    matches!(u.scheme(), "http" | "https")
+        && extra_hosts
+            .iter()
+            .any(|h| host == h || host.strip_suffix(h.as_str()).is_some_and(|p| p.ends_with('.')))
+}
+
 pub async fn fetch(url: &str, base_url: &str) -> Result<String, BotError> {
-    if !base_url.is_empty() && !same_host(url, base_url) {
+    if !host_allowed(url, base_url, &[]) {
         return Err(BotError::InternalError("SSRF blocked: host not allowed".to_string()));
     }
-    let resp = client::http().get(url).send().await?;
+    let started = std::time::Instant::now();
+    let resp = match client::http().get(url).send().await {
+        Ok(resp) => resp,
+        Err(e) => {
+            metrics::record_upstream(None, started.elapsed());
+            return Err(e.into());
+        }
+    };
     let status = resp.status();
+    metrics::record_upstream(Some(status.as_u16()), started.elapsed());
     if !status.is_success() {
         return Err(BotError::RequestStatusError(format!("{:?}", status)));
     }
@@ -28,6 +58,53 @@ pub async fn fetch(url: &str, base_url: &str) -> Result<String, BotError> {
     Ok(text)
 }
 
+/// 下载小文件（如封面）到内存，超过 `MAX_FETCH_BYTES` 时报错
+pub async fn fetch_bytes(url: &str) -> Result<Vec<u8>, BotError> {
+    let _permit = client::fetch_permit().await;
+    let resp = client::download().get(url).send().await?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(BotError::RequestStatusError(format!("{:?}", status)));
+    }
+    let too_large = || BotError::InvalidImage(format!("larger than {} bytes", MAX_FETCH_BYTES));
+    if resp.content_length().is_some_and(|len| len > MAX_FETCH_BYTES as u64) {
+        return Err(too_large());
+    }
+    // 逐块读取并按块申请带宽，与写入文件的下载方式一致
+    let mut bytes = Vec::new();
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > MAX_FETCH_BYTES {
+            return Err(too_large());
+        }
+        client::bandwidth().acquire(chunk.len()).await;
+        bytes.extend_from_slice(&chunk);
+        metrics::metrics().image_bytes.inc_by(chunk.len() as u64);
+    }
+    Ok(bytes)
+}
+
+/// 下载单页到内存并校验图片完整性，失败时按退避重试
+pub async fn fetch_page(url: &str) -> Result<Vec<u8>, BotError> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match fetch_bytes(url).await.and_then(|bytes| {
+            super::img::verify_image(&bytes)?;
+            Ok(bytes)
+        }) {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) if attempts >= MAX_ATTEMPTS => {
+                record_image_failure(&e);
+                return Err(e);
+            }
+            Err(e) => error!("下载失败 {} (第{}次): {:?}", url, attempts, e),
+        }
+        tokio::time::sleep(std::time::Duration::from_millis((100 * attempts).into())).await;
+    }
+}
+
 pub fn resolve_url(v: &str, base_url: &str) -> String {
     if v.starts_with("http") {
         return v.to_string();
@@ -51,71 +128,379 @@ pub fn resolve_url(v: &str, base_url: &str) -> String {
     v.to_string()
 }
 
-async fn download_file(
-    client: &reqwest::Client,
-    url: &str,
-    save_path: &str,
-) -> crate::error::Result<()> {
-    let mut attempt = 0u32;
-    let response = loop {
-        attempt += 1;
-        match client.get(url).send().await {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    break resp;
-                }
+static MAX_ATTEMPTS: u32 = 3;
+/// 单个文件读入内存的上限，正常的漫画页面远小于此
+static MAX_FETCH_BYTES: usize = 64 * 1024 * 1024;
+/// 页面文件名的最小位数（0001.webp）
+static PAGE_NAME_WIDTH: usize = 4;
+/// 已下载页面可能的扩展名，查找时依次探测
+static PAGE_EXTENSIONS: [&str; 5] = ["webp", "jpg", "png", "gif", "bin"];
+static MANIFEST_FILE: &str = "manifest.json";
+
+/// 单页下载结果，`index` 为该页在原始 url 列表中的位置（从 0 开始），
+/// `file_path` 为落盘路径（扩展名按实际图片类型确定，下载失败时为空），
+/// `corrupt` 表示最后一次失败是因为图片校验未通过
+#[derive(Debug, Clone)]
+pub struct PageResult {
+    pub index: usize,
+    pub url: String,
+    pub file_path: String,
+    pub attempts: u32,
+    pub bytes: u64,
+    pub error: Option<String>,
+    pub corrupt: bool,
+}
+
+impl PageResult {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// 一批页面的下载报告，按页序排列
+#[derive(Debug, Clone, Default)]
+pub struct DownloadReport {
+    pub pages: Vec<PageResult>,
+}
+
+impl DownloadReport {
+    pub fn total(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn succeeded(&self) -> usize {
+        self.pages.iter().filter(|p| p.is_ok()).count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &PageResult> {
+        self.pages.iter().filter(|p| !p.is_ok())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.pages.iter().all(|p| p.is_ok())
+    }
+
+    /// 缺失的页码（从 1 开始）
+    pub fn missing_pages(&self) -> Vec<usize> {
+        self.failed().map(|p| p.index + 1).collect()
+    }
+
+    /// 因图片损坏而缺失的页码（从 1 开始）
+    pub fn corrupted_pages(&self) -> Vec<usize> {
+        self.failed().filter(|p| p.corrupt).map(|p| p.index + 1).collect()
+    }
+
+    /// 用重试结果覆盖同一页的记录，尝试次数累加
+    pub fn merge(&mut self, retry: DownloadReport) {
+        for page in retry.pages {
+            if let Some(slot) = self.pages.iter_mut().find(|p| p.index == page.index) {
+                let attempts = slot.attempts + page.attempts;
+                *slot = PageResult { attempts, ..page };
+            } else {
+                self.pages.push(page);
             }
-            Err(_) => {}
         }
-        if attempt >= 3 {
-            return Err(crate::error::BotError::RequestStatusError(
-                "下载失败，超过重试次数".to_string(),
-            ));
+        self.pages.sort_by_key(|p| p.index);
+    }
+
+    /// 页码与原始 url、落盘文件的对应关系
+    pub fn manifest(&self) -> Vec<ManifestPage> {
+        self.pages
+            .iter()
+            .map(|p| ManifestPage {
+                page: p.index + 1,
+                url: p.url.clone(),
+                file: p.is_ok().then(|| {
+                    Path::new(&p.file_path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                }),
+                bytes: p.bytes,
+            })
+            .collect()
+    }
+}
+
+/// 清单中的一页：页码（从 1 开始）、原始 url 与落盘文件名
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ManifestPage {
+    pub page: usize,
+    pub url: String,
+    pub file: Option<String>,
+    pub bytes: u64,
+}
+
+/// 在下载目录写入 manifest.json，记录每页对应的原始 url；
+/// 只下载了部分页面时与已有清单合并，其余页面的记录保持不变
+pub async fn write_manifest(save_path: &str, report: &DownloadReport) -> crate::error::Result<()> {
+    let path = format!("{}/{}", save_path, MANIFEST_FILE);
+    let mut manifest: Vec<ManifestPage> = match tokio::fs::read(&path).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    let pages = report.manifest();
+    manifest.retain(|old| pages.iter().all(|p| p.page != old.page));
+    manifest.extend(pages);
+    manifest.sort_by_key(|p| p.page);
+
+    let bytes = serde_json::to_vec_pretty(&manifest)?;
+    tokio::fs::write(&path, bytes).await?;
+    Ok(())
+}
+
+/// 页面文件名（不含扩展名）：按页序补零编号，与 CDN 上的文件名无关
+pub fn page_name(index: usize, total: usize) -> String {
+    let width = total.to_string().len().max(PAGE_NAME_WIDTH);
+    format!("{:0width$}", index + 1)
+}
+
+/// 页面文件路径（不含扩展名）
+pub fn page_stem(save_path: &str, index: usize, total: usize) -> String {
+    format!("{}/{}", save_path, page_name(index, total))
+}
+
+/// 查找已下载的第 `index` 页
+pub fn find_page(save_path: &str, index: usize, total: usize) -> Option<String> {
+    let stem = page_stem(save_path, index, total);
+    PAGE_EXTENSIONS
+        .iter()
+        .map(|ext| format!("{}.{}", stem, ext))
+        .find(|path| std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.len() > 0))
+}
+
+/// 确定图片扩展名：优先文件头魔数，其次 Content-Type，最后 url 路径（去掉查询串）
+pub fn page_extension(head: &[u8], content_type: Option<&str>, url: &str) -> &'static str {
+    if let Some(ext) = super::img::sniff_extension(head) {
+        return ext;
+    }
+    let from_mime = match content_type.and_then(|c| c.split(';').next()).map(str::trim) {
+        Some("image/webp") => Some("webp"),
+        Some("image/jpeg") => Some("jpg"),
+        Some("image/png") => Some("png"),
+        Some("image/gif") => Some("gif"),
+        _ => None,
+    };
+    let from_url = || {
+        let path = Url::parse(url).ok()?.path().to_ascii_lowercase();
+        match path.rsplit_once('.')?.1 {
+            "webp" => Some("webp"),
+            "jpg" | "jpeg" => Some("jpg"),
+            "png" => Some("png"),
+            "gif" => Some("gif"),
+            _ => None,
         }
-        let delay = 100 * attempt; // 毫秒
-        tokio::time::sleep(std::time::Duration::from_millis(delay.into())).await;
     };
+    from_mime.or_else(from_url).unwrap_or("bin")
+}
 
-    // url 解析文件名
-    let filename = url.split('/').last().unwrap_or(url);
-    let file_path = format!("{}/{}", save_path, filename);
+/// 下载到 `{stem}.{ext}`，返回落盘路径和字节数；按全局与任务带宽限速
+async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    stem: &str,
+    limiter: &RateLimiter,
+) -> crate::error::Result<(String, u64)> {
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(BotError::RequestStatusError(format!("{:?}", status)));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    // 站点出错时常以 200 返回 HTML/JSON 提示页
+    if let Some(mime) = content_type.as_deref().and_then(|c| c.split(';').next()) {
+        let mime = mime.trim().to_ascii_lowercase();
+        if mime.starts_with("text/") || mime.contains("json") || mime.contains("xml") {
+            return Err(BotError::InvalidImage(format!("Content-Type 为 {}", mime)));
+        }
+    }
+    let expected = response.content_length();
 
-    // 创建目标文件
-    let path = Path::new(file_path.as_str());
-    if let Some(parent) = path.parent() {
+    // 先写入临时文件，完成后再 rename，避免中断留下半截文件
+    if let Some(parent) = Path::new(stem).parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
-    let mut file = File::create(path).await?;
+    // 临时文件名带随机后缀，避免同一目录下并发任务互相覆盖
+    let part_path = format!("{}.{}.part", stem, uuid::Uuid::new_v4().simple());
+    let mut file = File::create(&part_path).await?;
 
-    // 流式写入文件
+    // 流式写入文件，保留开头几个字节用于识别图片类型
+    let mut head = Vec::with_capacity(16);
+    let mut written = 0u64;
     let mut stream = response.bytes_stream();
     while let Some(chunk) = stream.next().await {
         let chunk = chunk?;
+        client::bandwidth().acquire(chunk.len()).await;
+        limiter.acquire(chunk.len()).await;
+        if head.len() < 16 {
+            head.extend(chunk.iter().take(16 - head.len()));
+        }
         file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+        metrics::metrics().image_bytes.inc_by(chunk.len() as u64);
     }
-
     file.flush().await?;
-    Ok(())
+    drop(file);
+
+    let verified = match expected {
+        Some(len) if len != written => {
+            Err(BotError::InvalidImage(format!("文件被截断 ({}/{} 字节)", written, len)))
+        }
+        _ => tokio::fs::read(&part_path)
+            .await
+            .map_err(BotError::from)
+            .and_then(|bytes| super::img::verify_image(&bytes).map(|_| ())),
+    };
+    if let Err(e) = verified {
+        tokio::fs::remove_file(&part_path).await.ok();
+        return Err(e);
+    }
+
+    let file_path = format!("{}.{}", stem, page_extension(&head, content_type.as_deref(), url));
+    tokio::fs::rename(&part_path, &file_path).await?;
+    Ok((file_path, written))
 }
 
-pub async fn download_batch(urls: Vec<String>, save_path: &str, max_concurrent: usize) {
+async fn download_page(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    index: usize,
+    url: String,
+    save_path: &str,
+    total: usize,
+) -> PageResult {
+    let mut page = PageResult {
+        index,
+        url,
+        file_path: String::new(),
+        attempts: 0,
+        bytes: 0,
+        error: None,
+        corrupt: false,
+    };
+
+    // 已完整下载的文件直接跳过（任务恢复时复用已下载的页面），校验不通过的删掉重新下载
+    if let Some(file_path) = find_page(save_path, index, total)
+        && let Ok(meta) = tokio::fs::metadata(&file_path).await
+    {
+        match super::img::verify_file(&file_path) {
+            Ok(_) => {
+                page.file_path = file_path;
+                page.bytes = meta.len();
+                return page;
+            }
+            Err(e) => {
+                error!("已下载的页面校验失败，重新下载 {}: {}", file_path, e);
+                tokio::fs::remove_file(&file_path).await.ok();
+            }
+        }
+    }
+
+    let stem = page_stem(save_path, index, total);
+    loop {
+        page.attempts += 1;
+        let permit = client::fetch_permit().await;
+        let result = download_file(client, &page.url, &stem, limiter).await;
+        drop(permit);
+        match result {
+            Ok((file_path, bytes)) => {
+                page.file_path = file_path;
+                page.bytes = bytes;
+                page.error = None;
+                page.corrupt = false;
+                return page;
+            }
+            Err(e) => {
+                error!("下载失败 {} (第{}次): {:?}", page.url, page.attempts, e);
+                page.corrupt = matches!(e, BotError::InvalidImage(_));
+                page.error = Some(e.to_string());
+                if page.attempts >= MAX_ATTEMPTS {
+                    record_image_failure(&e);
+                    return page;
+                }
+            }
+        }
+        let delay = 100 * page.attempts; // 毫秒
+        tokio::time::sleep(std::time::Duration::from_millis(delay.into())).await;
+    }
+}
+
+/// 重试用尽的图片按原因计数：损坏、HTTP 状态或网络错误
+fn record_image_failure(e: &BotError) {
+    let reason = match e {
+        BotError::InvalidImage(_) => "invalid",
+        BotError::RequestStatusError(_) => "status",
+        _ => "error",
+    };
+    metrics::metrics().image_failures.with_label_values(&[reason]).inc();
+}
+
+/// 批量下载的进度快照
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub bytes: u64,
+}
+
+/// 重新下载报告中失败的页面，并把结果合并回报告；`total` 为作品总页数
+pub async fn retry_failed(
+    report: &mut DownloadReport,
+    total: usize,
+    save_path: &str,
+    max_concurrent: usize,
+) {
+    let pages: Vec<(usize, String)> = report.failed().map(|p| (p.index, p.url.clone())).collect();
+    if pages.is_empty() {
+        return;
+    }
+    info!("重试失败页面: {}", pages.len());
+    let retry = download_pages(pages, total, save_path, max_concurrent, None).await;
+    report.merge(retry);
+}
+
+/// 下载作品中的部分页面，`pages` 为 (页序, url)，`total` 为作品总页数，决定文件名位数；
+/// 同一批页面共享一个任务带宽限速器
+pub async fn download_pages(
+    pages: Vec<(usize, String)>,
+    total: usize,
+    save_path: &str,
+    max_concurrent: usize,
+    progress: Option<&watch::Sender<DownloadProgress>>,
+) -> DownloadReport {
+    let limiter = client::job_bandwidth();
     let client = Arc::new(client::download());
+    let mut state = DownloadProgress { total: pages.len(), ..Default::default() };
+    let mut report = DownloadReport { pages: Vec::with_capacity(pages.len()) };
 
-    let results: Vec<_> = stream::iter(urls)
-        .map(|url| {
+    let mut results = stream::iter(pages)
+        .map(|(index, url)| {
             let client = Arc::clone(&client);
-            async move {
-                download_file(&client, &url, &save_path).await.map_err(|e| {
-                    error!("下载失败 {}: {:?}", url, e);
-                    (url, e)
-                })
-            }
+            let limiter = &limiter;
+            async move { download_page(&client, limiter, index, url, save_path, total).await }
         })
-        .buffer_unordered(max_concurrent) // 限制并发
-        .collect()
-        .await;
+        .buffer_unordered(max_concurrent); // 限制并发
+
+    while let Some(page) = results.next().await {
+        if page.is_ok() {
+            state.done += 1;
+            state.bytes += page.bytes;
+        } else {
+            state.failed += 1;
+        }
+        report.pages.push(page);
+        if let Some(tx) = progress {
+            tx.send_replace(state.clone());
+        }
+    }
+    report.pages.sort_by_key(|p| p.index);
 
     // 统计结果
-    let success = results.iter().filter(|r| r.is_ok()).count();
-    info!("下载完成，成功: {}, 失败: {}", success, results.len() - success);
+    info!("下载完成，成功: {}, 失败: {}", state.done, state.failed);
+    report
 }
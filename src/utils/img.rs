@@ -0,0 +1,188 @@
+use crate::error::{BotError, Result};
+use image::ImageFormat;
+use image::codecs::jpeg::JpegEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// 打包前对页面图片的转码方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Transcode {
+    /// 保留原图（站点默认为 WebP）
+    #[default]
+    Original,
+    Jpeg,
+    Png,
+}
+
+impl Transcode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "original" | "raw" => Some(Self::Original),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Original => "original",
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+        }
+    }
+
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Original => None,
+            Self::Jpeg => Some("jpg"),
+            Self::Png => Some("png"),
+        }
+    }
+}
+
+/// 根据文件头魔数识别图片格式，返回对应扩展名
+pub fn sniff_extension(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0xFF, 0xD8, 0xFF, ..] => Some("jpg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("webp"),
+        _ => None,
+    }
+}
+
+pub fn mime_type(ext: &str) -> &'static str {
+    match ext.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 小于该字节数的响应不可能是完整的页面图片（空文件、错误提示等）
+static MIN_IMAGE_BYTES: usize = 64;
+
+/// 校验下载到的图片是否完整可用：大小、魔数、图片头可解码且宽高非零、文件尾完整，
+/// 通过时返回识别出的扩展名
+pub fn verify_image(bytes: &[u8]) -> Result<&'static str> {
+    if bytes.len() < MIN_IMAGE_BYTES {
+        return Err(BotError::InvalidImage(format!("文件过小 ({} 字节)", bytes.len())));
+    }
+    let Some(ext) = sniff_extension(bytes) else {
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(64)]).to_ascii_lowercase();
+        let reason = if head.contains("<html") || head.contains("<!doctype") {
+            "内容为 HTML 页面"
+        } else {
+            "无法识别的图片格式"
+        };
+        return Err(BotError::InvalidImage(reason.to_string()));
+    };
+    let (width, height) = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| BotError::InvalidImage(e.to_string()))?
+        .into_dimensions()
+        .map_err(|e| BotError::InvalidImage(format!("图片头解析失败: {}", e)))?;
+    if width == 0 || height == 0 {
+        return Err(BotError::InvalidImage(format!("尺寸无效 {}x{}", width, height)));
+    }
+    if !has_trailer(ext, bytes) {
+        return Err(BotError::InvalidImage("文件被截断".to_string()));
+    }
+    Ok(ext)
+}
+
+/// 校验已落盘的图片
+pub fn verify_file(path: &str) -> Result<&'static str> {
+    verify_image(&std::fs::read(path)?)
+}
+
+/// 检查各格式的结束标记，识别传输中断导致的半截文件
+fn has_trailer(ext: &str, bytes: &[u8]) -> bool {
+    // 部分编码器会在结束标记后补零
+    let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    let tail = &bytes[end.saturating_sub(32)..end];
+    match ext {
+        "jpg" => tail.windows(2).any(|w| w == [0xFF, 0xD9]),
+        "png" => tail.windows(4).any(|w| w == b"IEND"),
+        "gif" => tail.last() == Some(&0x3B),
+        "webp" => {
+            let size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+            bytes.len() >= size + 8
+        }
+        _ => true,
+    }
+}
+
+/// 只解析图片头部获取宽高，不解码像素
+pub fn dimensions(path: &Path) -> Option<(u32, u32)> {
+    image::ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_dimensions().ok()
+}
+
+/// 转码结果的存放位置：页面目录下按转码方式分子目录，原图保留以便其他请求复用
+pub fn transcoded_path(page: &str, mode: Transcode) -> String {
+    let Some(ext) = mode.extension() else {
+        return page.to_string();
+    };
+    let path = Path::new(page);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    dir.join(mode.as_str()).join(format!("{}.{}", stem, ext)).to_string_lossy().to_string()
+}
+
+/// 查找已转码的页面；原图本就是目标格式时返回原图
+pub fn find_transcoded(page: &str, mode: Transcode) -> Option<String> {
+    let out = transcoded_path(page, mode);
+    if Path::new(&out).is_file() {
+        return Some(out);
+    }
+    let ext = Path::new(page).extension().and_then(|e| e.to_str());
+    (mode.extension().is_none_or(|target| ext == Some(target)) && Path::new(page).is_file())
+        .then(|| page.to_string())
+}
+
+/// 把页面转成目标格式（JPEG 按 `quality` 有损压缩，PNG 无损），返回转码后的文件路径；
+/// 原图已是目标格式或已转码过时直接复用
+pub fn transcode(page: &str, mode: Transcode, quality: u8) -> Result<String> {
+    let Some(ext) = mode.extension() else {
+        return Ok(page.to_string());
+    };
+    let bytes = std::fs::read(page)?;
+    if sniff_extension(&bytes) == Some(ext) {
+        return Ok(page.to_string());
+    }
+
+    let out = transcoded_path(page, mode);
+    if std::fs::metadata(&out).is_ok_and(|m| m.len() > 0) {
+        return Ok(out);
+    }
+    if let Some(parent) = Path::new(&out).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let img = image::load_from_memory(&bytes).map_err(image_error)?;
+    // 先写临时文件再 rename，避免中断留下半截图片被当成已转码
+    let part = format!("{}.{}.part", out, uuid::Uuid::new_v4().simple());
+    let mut writer = BufWriter::new(File::create(&part)?);
+    match mode {
+        Transcode::Jpeg => {
+            // JPEG 不支持透明通道
+            JpegEncoder::new_with_quality(&mut writer, quality.clamp(1, 100))
+                .encode_image(&img.to_rgb8())
+                .map_err(image_error)?;
+        }
+        _ => img.write_to(&mut writer, ImageFormat::Png).map_err(image_error)?,
+    }
+    writer.into_inner().map_err(|e| e.into_error())?;
+    std::fs::rename(&part, &out)?;
+    Ok(out)
+}
+
+pub fn image_error(e: image::ImageError) -> BotError {
+    BotError::InternalError(format!("image decode failed: {}", e))
+}
@@ -0,0 +1,129 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::time::Duration;
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// 进程内的 Prometheus 指标，`/metrics` 导出时再刷新任务数、缓存条目与磁盘占用等状态量
+pub struct Metrics {
+    registry: Registry,
+    pub commands: IntCounterVec,
+    pub command_duration: HistogramVec,
+    pub upstream_requests: IntCounterVec,
+    pub upstream_duration: Histogram,
+    pub image_bytes: IntCounter,
+    pub image_failures: IntCounterVec,
+    pub cache_lookups: IntCounterVec,
+    pub cache_evictions: IntCounterVec,
+    pub cache_entries: IntGaugeVec,
+    pub jobs: IntGaugeVec,
+    pub disk_usage: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("mangabot".to_string()), None).expect("metrics registry");
+
+        let commands = IntCounterVec::new(
+            Opts::new("commands_total", "已分发的机器人命令"),
+            &["command", "result"],
+        )
+        .unwrap();
+        let command_duration = HistogramVec::new(
+            HistogramOpts::new("command_duration_seconds", "命令处理耗时")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+            &["command"],
+        )
+        .unwrap();
+        let upstream_requests = IntCounterVec::new(
+            Opts::new("upstream_requests_total", "源站页面请求，按 HTTP 状态码或 error 分类"),
+            &["status"],
+        )
+        .unwrap();
+        let upstream_duration = Histogram::with_opts(
+            HistogramOpts::new("upstream_request_duration_seconds", "源站页面请求耗时")
+                .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0]),
+        )
+        .unwrap();
+        let image_bytes =
+            IntCounter::new("image_download_bytes_total", "已下载的图片字节数").unwrap();
+        let image_failures = IntCounterVec::new(
+            Opts::new("image_download_failures_total", "重试后仍失败的图片"),
+            &["reason"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "缓存查询，result 为 hit 或 miss"),
+            &["cache", "result"],
+        )
+        .unwrap();
+        let cache_evictions = IntCounterVec::new(
+            Opts::new("cache_evictions_total", "缓存驱逐，按原因分类"),
+            &["cache", "cause"],
+        )
+        .unwrap();
+        let cache_entries =
+            IntGaugeVec::new(Opts::new("cache_entries", "缓存条目数"), &["cache"]).unwrap();
+        let jobs = IntGaugeVec::new(Opts::new("jobs", "未完成的下载任务"), &["status"]).unwrap();
+        let disk_usage = IntGauge::new("download_dir_bytes", "下载目录占用的字节数").unwrap();
+
+        registry.register(Box::new(commands.clone())).unwrap();
+        registry.register(Box::new(command_duration.clone())).unwrap();
+        registry.register(Box::new(upstream_requests.clone())).unwrap();
+        registry.register(Box::new(upstream_duration.clone())).unwrap();
+        registry.register(Box::new(image_bytes.clone())).unwrap();
+        registry.register(Box::new(image_failures.clone())).unwrap();
+        registry.register(Box::new(cache_lookups.clone())).unwrap();
+        registry.register(Box::new(cache_evictions.clone())).unwrap();
+        registry.register(Box::new(cache_entries.clone())).unwrap();
+        registry.register(Box::new(jobs.clone())).unwrap();
+        registry.register(Box::new(disk_usage.clone())).unwrap();
+
+        Self {
+            registry,
+            commands,
+            command_duration,
+            upstream_requests,
+            upstream_duration,
+            image_bytes,
+            image_failures,
+            cache_lookups,
+            cache_evictions,
+            cache_entries,
+            jobs,
+            disk_usage,
+        }
+    }
+
+    /// 文本格式的全部指标
+    pub fn render(&self) -> String {
+        let mut buf = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buf);
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub fn record_command(command: &str, ok: bool, elapsed: Duration) {
+    let m = metrics();
+    m.commands.with_label_values(&[command, if ok { "ok" } else { "error" }]).inc();
+    m.command_duration.with_label_values(&[command]).observe(elapsed.as_secs_f64());
+}
+
+pub fn record_upstream(status: Option<u16>, elapsed: Duration) {
+    let m = metrics();
+    let status = status.map_or_else(|| "error".to_string(), |s| s.to_string());
+    m.upstream_requests.with_label_values(&[status.as_str()]).inc();
+    m.upstream_duration.observe(elapsed.as_secs_f64());
+}
+
+pub fn record_cache_lookup(cache: &str, hit: bool) {
+    metrics().cache_lookups.with_label_values(&[cache, if hit { "hit" } else { "miss" }]).inc();
+}
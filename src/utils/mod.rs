@@ -1,12 +1,19 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+pub mod archive;
 pub mod cache;
 pub mod client;
 pub mod codec;
 pub mod dom;
+pub mod epub;
 pub mod fs;
 pub mod http;
+pub mod img;
+pub mod metrics;
+pub mod pages;
+pub mod pdf;
+pub mod throttle;
 pub mod zip;
 
 static NUM_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"-(\d+)").unwrap());
@@ -33,3 +40,62 @@ pub fn escape_md_v2(s: &str) -> String {
     }
     out
 }
+
+/// 转义文本与属性值，XML 与 HTML 页面通用（单引号用数字引用，HTML4 不认 `&apos;`）
+pub fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// 比较耗时与内容无关，用于校验密钥，避免按字节提前返回泄露信息
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// 解析带单位的字节数，如 `512K`、`1.5M`、`2MB`，不带单位按字节计
+pub fn parse_bytes(s: &str) -> Option<u64> {
+    let s = s.trim().to_ascii_uppercase();
+    let s = s.strip_suffix('B').unwrap_or(&s);
+    let (num, unit) = match s.char_indices().last()? {
+        (i, 'K') => (&s[..i], 1024u64),
+        (i, 'M') => (&s[..i], 1024 * 1024),
+        (i, 'G') => (&s[..i], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    let value: f64 = num.trim().parse().ok()?;
+    (value.is_finite() && value >= 0.0).then_some((value * unit as f64) as u64)
+}
+
+pub fn human_duration(d: std::time::Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..60 => format!("{}秒", secs),
+        60..3600 => format!("{}分{}秒", secs / 60, secs % 60),
+        _ => format!("{}时{}分", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+/// 把有序页码压缩为区间表示，如 `[1, 2, 3, 7, 9, 10]` -> `1-3, 7, 9-10`
+pub fn format_ranges(pages: &[usize]) -> String {
+    let mut parts = Vec::new();
+    let mut iter = pages.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end = iter.next().unwrap_or(end);
+        }
+        if start == end {
+            parts.push(start.to_string());
+        } else {
+            parts.push(format!("{}-{}", start, end));
+        }
+    }
+    parts.join(", ")
+}
+
+/// 解析页码区间，如 `1-20,35`、`30-`（到最后一页），返回去重排序后的页码（从 1 开始）；
+/// 超出 `total` 的部分被忽略，格式错误时返回 None
+pub fn parse_ranges(s: &str, total: usize) -> Option<Vec<usize>> {
+    let s = s.strip_prefix(['p', 'P']).unwrap_or(s);
+    let mut pages = BTreeSet::new();
+    for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, "")) => (a.trim().parse().ok()?, total),
+            Some((a, b)) => (a.trim().parse().ok()?, b.trim().parse().ok()?),
+            None => {
+                let n = part.parse().ok()?;
+                (n, n)
+            }
+        };
+        if start == 0 || start > end {
+            return None;
+        }
+        pages.extend(start..=end.min(total));
+    }
+    Some(pages.into_iter().collect())
+}
+
+/// 参数是否为页码区间写法：由数字、`-`、`,` 组成且至少含一个分隔符，单页可写作 `p35`
+pub fn is_range_spec(s: &str) -> bool {
+    let (body, prefixed) = match s.strip_prefix(['p', 'P']) {
+        Some(rest) => (rest, true),
+        None => (s, false),
+    };
+    !body.is_empty()
+        && body.chars().any(|c| c.is_ascii_digit())
+        && body.chars().all(|c| c.is_ascii_digit() || c == '-' || c == ',')
+        && (prefixed || body.contains(['-', ',']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_ranges() {
+        assert_eq!(format_ranges(&[]), "");
+        assert_eq!(format_ranges(&[4]), "4");
+        assert_eq!(format_ranges(&[1, 2, 3, 7, 9, 10]), "1-3, 7, 9-10");
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse_ranges("1-3,7", 10), Some(vec![1, 2, 3, 7]));
+        assert_eq!(parse_ranges("8-, 2-3, 3", 10), Some(vec![2, 3, 8, 9, 10]));
+        assert_eq!(parse_ranges("9-20", 10), Some(vec![9, 10]));
+        assert_eq!(parse_ranges("11-12", 10), Some(vec![]));
+        assert_eq!(parse_ranges("p4", 10), Some(vec![4]));
+        assert_eq!(parse_ranges("0-3", 10), None);
+        assert_eq!(parse_ranges("5-2", 10), None);
+        assert_eq!(parse_ranges("a-b", 10), None);
+    }
+
+    #[test]
+    fn test_is_range_spec() {
+        assert!(is_range_spec("1-20,35"));
+        assert!(is_range_spec("30-"));
+        assert!(is_range_spec("p35"));
+        assert!(!is_range_spec("35"));
+        assert!(!is_range_spec("png"));
+        assert!(!is_range_spec("-"));
+    }
+}
@@ -0,0 +1,203 @@
+use super::archive::ArchiveSource;
+use super::img::image_error;
+use crate::error::Result;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use image::ImageDecoder;
+use image::codecs::jpeg::JpegDecoder;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// 每页占用的对象数：页面、内容流、图片
+static OBJECTS_PER_PAGE: usize = 3;
+/// 页面对象从 4 开始编号（1 目录、2 页面树、3 文档信息）
+static FIRST_PAGE_OBJECT: usize = 4;
+/// 页面对象的固定前缀，校验时据此数页
+static PAGE_MARKER: &[u8] = b"<< /Type /Page /Parent";
+
+/// 嵌入 PDF 的页面图片
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    /// JPEG 原样嵌入（DCTDecode），其他格式解码后以 FlateDecode 压缩
+    filter: &'static str,
+    /// CMYK JPEG（Adobe 约定为反相存储）需要反转解码范围
+    decode: Option<&'static str>,
+    data: Vec<u8>,
+}
+
+impl PdfImage {
+    fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        if super::img::sniff_extension(&bytes) == Some("jpg") {
+            return Self::jpeg(bytes);
+        }
+
+        // PNG/WebP/GIF 等统一转成 RGB 后压缩（透明通道直接丢弃）
+        let rgb = image::load_from_memory(&bytes).map_err(image_error)?.to_rgb8();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(rgb.as_raw())?;
+        Ok(Self {
+            width: rgb.width(),
+            height: rgb.height(),
+            color_space: "/DeviceRGB",
+            filter: "/FlateDecode",
+            decode: None,
+            data: encoder.finish()?,
+        })
+    }
+
+    /// 只解析 JPEG 头部获取尺寸与色彩空间，图片数据原样写入
+    fn jpeg(bytes: Vec<u8>) -> Result<Self> {
+        let decoder = JpegDecoder::new(std::io::Cursor::new(&bytes)).map_err(image_error)?;
+        let (width, height) = decoder.dimensions();
+        let (color_space, decode) = match decoder.original_color_type() {
+            image::ExtendedColorType::L8 => ("/DeviceGray", None),
+            image::ExtendedColorType::Cmyk8 => ("/DeviceCMYK", Some("[1 0 1 0 1 0 1 0]")),
+            _ => ("/DeviceRGB", None),
+        };
+        Ok(Self { width, height, color_space, filter: "/DCTDecode", decode, data: bytes })
+    }
+}
+
+/// 记录每个对象起始偏移的 PDF 写入器
+struct PdfWriter<W: Write> {
+    out: W,
+    pos: u64,
+    offsets: Vec<u64>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes)?;
+        self.pos += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn begin_object(&mut self, id: usize) -> Result<()> {
+        if self.offsets.len() < id {
+            self.offsets.resize(id, 0);
+        }
+        self.offsets[id - 1] = self.pos;
+        self.write(format!("{} 0 obj\n", id).as_bytes())
+    }
+
+    fn object(&mut self, id: usize, body: &str) -> Result<()> {
+        self.begin_object(id)?;
+        self.write(body.as_bytes())?;
+        self.write(b"\nendobj\n")
+    }
+
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) -> Result<()> {
+        self.begin_object(id)?;
+        self.write(format!("<< {} /Length {} >>\nstream\n", dict, data.len()).as_bytes())?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    fn finish(mut self) -> Result<W> {
+        let xref = self.pos;
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            xref
+        ));
+        self.write(table.as_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// PDF 文本字符串：UTF-16BE 加 BOM 的十六进制形式，兼容中文
+fn pdf_text(s: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in s.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+/// 每张图片一页、页面尺寸等于图片像素尺寸（保持原始宽高比），文档信息取自作品详情
+pub fn write_pdf(src: &ArchiveSource, pdf_path: &str) -> Result<()> {
+    let detail = src.detail;
+    let file = File::create(pdf_path)?;
+    let mut pdf = PdfWriter { out: BufWriter::new(file), pos: 0, offsets: Vec::new() };
+    pdf.write(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n")?;
+
+    let page_ids: Vec<usize> =
+        (0..src.pages.len()).map(|i| FIRST_PAGE_OBJECT + i * OBJECTS_PER_PAGE).collect();
+    pdf.object(1, "<< /Type /Catalog /Pages 2 0 R >>")?;
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    pdf.object(
+        2,
+        &format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_ids.len()),
+    )?;
+
+    let mut info = vec![
+        format!("/Title {}", pdf_text(&detail.title)),
+        format!("/Creator {}", pdf_text(env!("CARGO_PKG_NAME"))),
+        format!("/Producer {}", pdf_text(env!("CARGO_PKG_NAME"))),
+        format!("/CreationDate (D:{})", chrono::Utc::now().format("%Y%m%d%H%M%SZ")),
+    ];
+    if !detail.author.is_empty() {
+        info.push(format!("/Author {}", pdf_text(&detail.author)));
+    }
+    if !detail.description.is_empty() {
+        info.push(format!("/Subject {}", pdf_text(&detail.description)));
+    }
+    let keywords: Vec<&str> = std::iter::once(detail.category.as_str())
+        .chain(detail.tags.iter().map(String::as_str))
+        .filter(|s| !s.is_empty())
+        .collect();
+    if !keywords.is_empty() {
+        info.push(format!("/Keywords {}", pdf_text(&keywords.join(", "))));
+    }
+    pdf.object(3, &format!("<< {} >>", info.join(" ")))?;
+
+    for (page, id) in src.pages.iter().zip(page_ids) {
+        let img = PdfImage::load(Path::new(page))?;
+        let (w, h) = (img.width, img.height);
+        let (content_id, image_id) = (id + 1, id + 2);
+
+        pdf.object(
+            id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {w} {h}] \
+                 /Resources << /XObject << /Im0 {image_id} 0 R >> >> /Contents {content_id} 0 R >>"
+            ),
+        )?;
+        pdf.stream(content_id, "", format!("q {w} 0 0 {h} 0 0 cm /Im0 Do Q").as_bytes())?;
+
+        let mut dict = format!(
+            "/Type /XObject /Subtype /Image /Width {w} /Height {h} /ColorSpace {} \
+             /BitsPerComponent 8 /Filter {}",
+            img.color_space, img.filter
+        );
+        if let Some(decode) = img.decode {
+            dict.push_str(&format!(" /Decode {}", decode));
+        }
+        pdf.stream(image_id, &dict, &img.data)?;
+    }
+
+    pdf.finish()?;
+    Ok(())
+}
+
+/// 校验 PDF 是否完整写出（以 %%EOF 结尾）且页数符合预期
+pub fn verify_pdf(pdf_path: &str, expected_pages: usize) -> bool {
+    let Ok(bytes) = std::fs::read(pdf_path) else {
+        return false;
+    };
+    if !bytes.trim_ascii_end().ends_with(b"%%EOF") {
+        return false;
+    }
+    let pages = bytes.windows(PAGE_MARKER.len()).filter(|w| *w == PAGE_MARKER).count();
+    pages == expected_pages
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct Bucket {
+    /// 可用字节数，允许为负（透支），透支部分需等待补足
+    tokens: f64,
+    last: Instant,
+}
+
+/// 令牌桶限速器，速率单位为字节/秒，0 表示不限速；
+/// 速率可与其他限速器共享，运行时修改后立即对所有持有者生效
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: Arc<AtomicU64>,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: Arc<AtomicU64>) -> Self {
+        Self { rate, bucket: Mutex::new(Bucket { tokens: 0.0, last: Instant::now() }) }
+    }
+
+    pub fn with_rate(bytes_per_sec: u64) -> Self {
+        Self::new(Arc::new(AtomicU64::new(bytes_per_sec)))
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rate(&self, bytes_per_sec: u64) {
+        self.rate.store(bytes_per_sec, Ordering::Relaxed);
+    }
+
+    /// 消耗 `bytes` 个令牌，不足时等待；突发上限为一秒的流量
+    pub async fn acquire(&self, bytes: usize) {
+        let rate = self.rate();
+        if rate == 0 {
+            return;
+        }
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last).as_secs_f64() * rate as f64;
+            bucket.tokens = (bucket.tokens + refill).min(rate as f64) - bytes as f64;
+            bucket.last = now;
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / rate as f64))
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
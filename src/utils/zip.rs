@@ -1,44 +1,228 @@
 use std::fs::File;
 use std::io;
 use std::path::Path;
-use walkdir::WalkDir;
-use zip::ZipWriter;
 use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
 
-pub fn compress_dir(dir: &str, zip_path: &str) -> crate::error::Result<()> {
-    let base_path = Path::new(dir);
+/// 按给定顺序把文件写入压缩包根目录，保留原文件名
+pub fn compress_files(files: &[String], zip_path: &str) -> crate::error::Result<()> {
+    let file = File::create(zip_path)?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
 
-    if !base_path.exists() {
-        return Err(crate::error::BotError::InternalError(format!("dir not exists: {}", dir)));
+    for file in files {
+        let path = Path::new(file);
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        zip.start_file(name, options)?;
+        let mut f = File::open(path)?;
+        io::copy(&mut f, &mut zip)?;
     }
 
-    if !base_path.is_dir() {
-        return Err(crate::error::BotError::InternalError(format!(
-            "dir is not a directory: {}",
-            dir
-        )));
-    }
+    zip.finish()?;
+    Ok(())
+}
 
+/// 按给定顺序把页面写入压缩包，条目名为从 1 开始的补零序号（保留原扩展名），
+/// 图片本身已压缩，直接存储；`extra` 为附加的元数据文件
+pub fn compress_pages(
+    pages: &[String],
+    extra: &[(&str, Vec<u8>)],
+    zip_path: &str,
+) -> crate::error::Result<()> {
     let file = File::create(zip_path)?;
     let mut zip = ZipWriter::new(file);
-    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
 
-    // 遍历目录树
-    for entry in WalkDir::new(base_path) {
-        let entry = entry?;
-        let path = entry.path();
+    for (i, page) in pages.iter().enumerate() {
+        zip.start_file(entry_name(page, i, pages.len()), options)?;
+        let mut f = File::open(page)?;
+        io::copy(&mut f, &mut zip)?;
+    }
 
-        let relative_path = path.strip_prefix(base_path).unwrap_or(path);
+    let deflated = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, bytes) in extra {
+        zip.start_file(*name, deflated)?;
+        io::Write::write_all(&mut zip, bytes)?;
+    }
 
-        if path.is_file() {
-            zip.start_file(relative_path.to_string_lossy(), options)?;
-            let mut f = File::open(path)?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// 合并打包多部作品：每部作品一个目录，目录内的页面与 `compress_pages` 一样按序号命名
+pub fn compress_folders(
+    folders: &[(String, Vec<String>)],
+    zip_path: &str,
+) -> crate::error::Result<()> {
+    let file = File::create(zip_path)?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    for (folder, pages) in folders {
+        // 目录名中的路径分隔符会被解压工具当成子目录
+        let folder = folder.replace(['/', '\\'], "_");
+        zip.add_directory(folder.as_str(), options)?;
+        for (i, page) in pages.iter().enumerate() {
+            zip.start_file(format!("{}/{}", folder, entry_name(page, i, pages.len())), options)?;
+            let mut f = File::open(page)?;
             io::copy(&mut f, &mut zip)?;
-        } else if path != base_path {
-            zip.add_directory(relative_path.to_string_lossy(), options)?;
         }
     }
 
     zip.finish()?;
     Ok(())
 }
+
+/// 页面在压缩包中的条目名：从 1 开始的补零序号，保留原扩展名
+fn entry_name(page: &str, index: usize, total: usize) -> String {
+    let width = total.to_string().len().max(4);
+    match Path::new(page).extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{:0width$}.{}", index + 1, ext),
+        None => format!("{:0width$}", index + 1),
+    }
+}
+
+/// 校验已有压缩包是否可读且包含预期数量的文件（用于复用已打包的作品）
+pub fn verify_archive(zip_path: &str, expected_files: usize) -> bool {
+    let Ok(file) = File::open(zip_path) else {
+        return false;
+    };
+    let Ok(mut archive) = ZipArchive::new(file) else {
+        return false;
+    };
+
+    let mut files = 0;
+    for i in 0..archive.len() {
+        match archive.by_index(i) {
+            Ok(entry) if entry.is_file() => files += 1,
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+    files == expected_files
+}
+
+/// 不落盘、边生成边输出的 zip 编码器：条目不压缩（Stored），CRC 与大小写在数据描述符中，
+/// 无需预知内容长度；不支持 zip64，总长度超过 4GB 或条目超过 65535 个时返回错误
+#[derive(Default)]
+pub struct ZipStream {
+    offset: u64,
+    entries: Vec<StreamEntry>,
+    current: Option<StreamEntry>,
+}
+
+struct StreamEntry {
+    name: String,
+    offset: u32,
+    crc: crc32fast::Hasher,
+    size: u32,
+}
+
+/// bit 3: 使用数据描述符；bit 11: 文件名为 UTF-8
+static STREAM_FLAGS: u16 = 0x0008 | 0x0800;
+/// DOS 日期 1980-01-01
+static DOS_DATE: u16 = (1 << 5) | 1;
+
+impl ZipStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输出 `len` 字节后的偏移，超出 32 位字段的表示范围时返回错误
+    fn advance(&mut self, len: usize) -> crate::error::Result<()> {
+        let offset = self.offset + len as u64;
+        if offset > u32::MAX as u64 {
+            return Err(zip::result::ZipError::UnsupportedArchive("zip64 is not supported").into());
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    /// 开始新条目，返回需要输出的本地文件头
+    pub fn start_entry(&mut self, name: &str) -> crate::error::Result<Vec<u8>> {
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(zip::result::ZipError::UnsupportedArchive("too many entries").into());
+        }
+        let mut out = Vec::with_capacity(30 + name.len());
+        out.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        out.extend_from_slice(&20u16.to_le_bytes()); // 解压所需版本
+        out.extend_from_slice(&STREAM_FLAGS.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // Stored
+        out.extend_from_slice(&0u16.to_le_bytes()); // 修改时间
+        out.extend_from_slice(&DOS_DATE.to_le_bytes());
+        out.extend_from_slice(&[0; 12]); // CRC 与大小写在数据描述符中
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // 扩展字段长度
+        out.extend_from_slice(name.as_bytes());
+
+        let offset = self.offset as u32;
+        self.advance(out.len())?;
+        self.current = Some(StreamEntry {
+            name: name.to_string(),
+            offset,
+            crc: crc32fast::Hasher::new(),
+            size: 0,
+        });
+        Ok(out)
+    }
+
+    /// 记录当前条目的一段内容，内容本身由调用方原样输出
+    pub fn update(&mut self, data: &[u8]) -> crate::error::Result<()> {
+        self.advance(data.len())?;
+        let entry = self.current.as_mut().expect("no entry started");
+        entry.crc.update(data);
+        // 条目长度不超过总偏移，已在 advance 中检查
+        entry.size += data.len() as u32;
+        Ok(())
+    }
+
+    /// 结束当前条目，返回数据描述符
+    pub fn finish_entry(&mut self) -> crate::error::Result<Vec<u8>> {
+        let entry = self.current.take().expect("no entry started");
+        let crc = entry.crc.clone().finalize();
+        let mut out = Vec::with_capacity(16);
+        out.extend_from_slice(&0x08074b50u32.to_le_bytes());
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&entry.size.to_le_bytes());
+        out.extend_from_slice(&entry.size.to_le_bytes());
+        self.advance(out.len())?;
+        self.entries.push(entry);
+        Ok(out)
+    }
+
+    /// 返回中央目录与结束记录
+    pub fn finish(self) -> crate::error::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for entry in &self.entries {
+            out.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            out.extend_from_slice(&20u16.to_le_bytes()); // 创建版本
+            out.extend_from_slice(&20u16.to_le_bytes()); // 解压所需版本
+            out.extend_from_slice(&STREAM_FLAGS.to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(&DOS_DATE.to_le_bytes());
+            out.extend_from_slice(&entry.crc.clone().finalize().to_le_bytes());
+            out.extend_from_slice(&entry.size.to_le_bytes());
+            out.extend_from_slice(&entry.size.to_le_bytes());
+            out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&[0; 12]); // 扩展字段、注释、磁盘号、内部与外部属性
+            out.extend_from_slice(&entry.offset.to_le_bytes());
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+
+        // 中央目录的起始偏移与长度同样是 32 位字段
+        if self.offset + out.len() as u64 > u32::MAX as u64 {
+            return Err(zip::result::ZipError::UnsupportedArchive("zip64 is not supported").into());
+        }
+        let count = self.entries.len() as u16;
+        let directory_size = out.len() as u32;
+        out.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]); // 磁盘号
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&directory_size.to_le_bytes());
+        out.extend_from_slice(&(self.offset as u32).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // 注释长度
+        Ok(out)
+    }
+}
@@ -0,0 +1,105 @@
+//! 集成测试共用的测试数据与极简 HTTP 服务
+#![allow(dead_code)]
+
+use mangabot_rs::models::MangaDetail;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// 作品详情：只填编号、标题与页数，其余字段按需覆盖
+pub fn detail(id: i64, title: &str, total: i32) -> MangaDetail {
+    MangaDetail {
+        id,
+        title: title.to_string(),
+        cover: String::new(),
+        author: String::new(),
+        total,
+        category: String::new(),
+        tags: Vec::new(),
+        description: String::new(),
+    }
+}
+
+/// 16×16 的测试图片
+pub fn encode(format: image::ImageFormat) -> Vec<u8> {
+    encode_sized(format, 16)
+}
+
+/// size×size 的 PNG 图片，尺寸不同内容也不同
+pub fn encode_png(size: u32) -> Vec<u8> {
+    encode_sized(image::ImageFormat::Png, size)
+}
+
+fn encode_sized(format: image::ImageFormat, size: u32) -> Vec<u8> {
+    let img = image::RgbImage::from_fn(size, size, |x, y| {
+        image::Rgb([(x * 16) as u8, (y * 16) as u8, 0])
+    });
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buf, format).unwrap();
+    buf.into_inner()
+}
+
+/// 按顺序编号的页面列表
+pub fn pages(urls: &[String]) -> Vec<(usize, String)> {
+    urls.iter().cloned().enumerate().collect()
+}
+
+/// 测试服务的响应
+pub struct Reply {
+    status: &'static str,
+    content_type: Option<&'static str>,
+    /// Content-Length，None 时不发送
+    length: Option<usize>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    pub fn ok(body: Vec<u8>) -> Self {
+        Self { status: "200 OK", content_type: None, length: Some(body.len()), body }
+    }
+
+    pub fn not_found() -> Self {
+        Self { status: "404 Not Found", content_type: None, length: Some(0), body: Vec::new() }
+    }
+
+    pub fn content_type(mut self, content_type: &'static str) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+
+    /// 覆盖声明的长度，用于模拟截断或超大的响应
+    pub fn length(mut self, length: Option<usize>) -> Self {
+        self.length = length;
+        self
+    }
+}
+
+/// 极简 HTTP 服务：按请求路径（含查询参数）调用 `route` 生成响应，每个连接只处理一个请求
+pub async fn serve<F>(listener: TcpListener, route: F)
+where
+    F: Fn(&str) -> Reply + Send + Sync + 'static,
+{
+    let route = Arc::new(route);
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            return;
+        };
+        let route = route.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 4096];
+            let n = stream.read(&mut buf).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let reply = route(request.split_whitespace().nth(1).unwrap_or("/"));
+            let content_type =
+                reply.content_type.map(|t| format!("Content-Type: {}\r\n", t)).unwrap_or_default();
+            let length =
+                reply.length.map(|n| format!("Content-Length: {}\r\n", n)).unwrap_or_default();
+            let head = format!(
+                "HTTP/1.1 {}\r\n{}{}Connection: close\r\n\r\n",
+                reply.status, content_type, length
+            );
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(&reply.body).await;
+        });
+    }
+}
@@ -0,0 +1,37 @@
+use mangabot_rs::utils::http::{DownloadReport, PageResult};
+
+fn page(index: usize, error: Option<&str>) -> PageResult {
+    PageResult {
+        index,
+        url: format!("https://example.com/{}.webp", index),
+        file_path: format!("/tmp/{}.webp", index),
+        attempts: 3,
+        bytes: if error.is_none() { 100 } else { 0 },
+        error: error.map(|e| e.to_string()),
+        corrupt: false,
+    }
+}
+
+#[test]
+fn test_report_missing_pages() {
+    let report = DownloadReport {
+        pages: vec![page(0, None), page(1, Some("timeout")), page(2, Some("404"))],
+    };
+    assert!(!report.is_complete());
+    assert_eq!(report.succeeded(), 1);
+    assert_eq!(report.missing_pages(), vec![2, 3]);
+}
+
+#[test]
+fn test_report_merge_retry() {
+    let mut report = DownloadReport {
+        pages: vec![page(0, None), page(1, Some("timeout")), page(2, Some("404"))],
+    };
+    let mut retried = page(1, None);
+    retried.attempts = 1;
+    report.merge(DownloadReport { pages: vec![retried] });
+
+    assert_eq!(report.missing_pages(), vec![3]);
+    assert_eq!(report.pages[1].attempts, 4);
+    assert_eq!(report.total(), 3);
+}
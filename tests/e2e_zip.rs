@@ -1,17 +1,310 @@
-use mangabot_rs::utils::zip::compress_dir;
+mod common;
+
+use mangabot_rs::utils::zip::{compress_files, verify_archive};
 use std::fs;
 
 #[test]
-fn test_e2e_compress_dir_creates_zip() {
+fn test_e2e_compress_files_creates_zip() {
     let tmp = tempfile::tempdir().unwrap();
     let dir = tmp.path().join("manga");
     fs::create_dir_all(&dir).unwrap();
     fs::write(dir.join("a.txt"), b"hello").unwrap();
     fs::write(dir.join("b.txt"), b"world").unwrap();
+    let files = vec![
+        dir.join("a.txt").to_string_lossy().to_string(),
+        dir.join("b.txt").to_string_lossy().to_string(),
+    ];
 
     let zip_path = tmp.path().join("out.zip");
-    compress_dir(dir.to_str().unwrap(), zip_path.to_str().unwrap()).unwrap();
+    compress_files(&files, zip_path.to_str().unwrap()).unwrap();
 
     let meta = fs::metadata(&zip_path).unwrap();
     assert!(meta.len() > 0);
 }
+
+#[test]
+fn test_verify_archive_counts_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("manga");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("1.webp"), b"a").unwrap();
+    fs::write(dir.join("2.webp"), b"b").unwrap();
+    fs::write(dir.join("3.webp.part"), b"partial").unwrap();
+    let files = vec![
+        dir.join("1.webp").to_string_lossy().to_string(),
+        dir.join("2.webp").to_string_lossy().to_string(),
+    ];
+
+    let zip_path = tmp.path().join("out.zip");
+    let zip_path = zip_path.to_str().unwrap();
+    compress_files(&files, zip_path).unwrap();
+
+    // 目录中未下载完成的临时文件不会进入压缩包
+    assert!(verify_archive(zip_path, 2));
+    assert!(!verify_archive(zip_path, 3));
+    assert!(!verify_archive(tmp.path().join("missing.zip").to_str().unwrap(), 2));
+}
+
+#[test]
+fn test_cbz_pages_in_reading_order_with_comic_info() {
+    use mangabot_rs::models::MangaDetail;
+    use mangabot_rs::utils::archive::{ArchiveFormat, ArchiveSource, build};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("manga");
+    fs::create_dir_all(&dir).unwrap();
+    // 原始文件名的字典序与阅读顺序相反
+    let pages: Vec<String> = ["z.webp", "a.webp"]
+        .iter()
+        .map(|name| {
+            let path = dir.join(name);
+            fs::write(&path, name.as_bytes()).unwrap();
+            path.to_string_lossy().to_string()
+        })
+        .collect();
+
+    let detail = MangaDetail {
+        author: "author".to_string(),
+        category: "同人志".to_string(),
+        tags: vec!["t1".to_string(), "t2".to_string()],
+        ..common::detail(1, "A & B", 2)
+    };
+    let src = ArchiveSource {
+        dir: dir.to_str().unwrap(),
+        pages: &pages,
+        detail: &detail,
+        source_url: "https://example.com/photos-index-aid-1.html",
+        cover: None,
+    };
+    let out = tmp.path().join("out.cbz");
+    build(ArchiveFormat::Cbz, &src, out.to_str().unwrap()).unwrap();
+    assert!(verify_archive(out.to_str().unwrap(), ArchiveFormat::Cbz.expected_entries(2)));
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(&out).unwrap()).unwrap();
+    let mut first = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("0001.webp").unwrap(), &mut first).unwrap();
+    assert_eq!(first, "z.webp");
+
+    let mut info = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("ComicInfo.xml").unwrap(), &mut info)
+        .unwrap();
+    assert!(info.contains("<Title>A &amp; B</Title>"));
+    assert!(info.contains("<Writer>author</Writer>"));
+    assert!(info.contains("<Genre>同人志</Genre>"));
+    assert!(info.contains("<Tags>t1,t2</Tags>"));
+    assert!(info.contains("<PageCount>2</PageCount>"));
+}
+
+#[test]
+fn test_epub_fixed_layout() {
+    use mangabot_rs::models::MangaDetail;
+    use mangabot_rs::utils::archive::{ArchiveFormat, ArchiveSource, build};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("manga");
+    fs::create_dir_all(&dir).unwrap();
+    let pages: Vec<String> = ["b.png", "a.png"]
+        .iter()
+        .map(|name| {
+            let path = dir.join(name);
+            image::RgbImage::new(30, 50).save(&path).unwrap();
+            path.to_string_lossy().to_string()
+        })
+        .collect();
+
+    let detail = MangaDetail {
+        author: "author".to_string(),
+        tags: vec!["t1".to_string()],
+        ..common::detail(1, "A & B", 2)
+    };
+    let src = ArchiveSource {
+        dir: dir.to_str().unwrap(),
+        pages: &pages,
+        detail: &detail,
+        source_url: "https://example.com/photos-index-aid-1.html",
+        cover: None,
+    };
+    let out = tmp.path().join("out.epub");
+    build(ArchiveFormat::Epub, &src, out.to_str().unwrap()).unwrap();
+    assert!(verify_archive(out.to_str().unwrap(), ArchiveFormat::Epub.expected_entries(2)));
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(&out).unwrap()).unwrap();
+    {
+        let mut mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut mimetype, &mut text).unwrap();
+        assert_eq!(text, "application/epub+zip");
+    }
+
+    let mut opf = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("OEBPS/content.opf").unwrap(), &mut opf)
+        .unwrap();
+    assert!(opf.contains("<dc:title>A &amp; B</dc:title>"));
+    assert!(opf.contains("<dc:creator>author</dc:creator>"));
+    assert!(opf.contains("<dc:subject>t1</dc:subject>"));
+    assert!(opf.contains("pre-paginated"));
+    assert!(opf.contains("properties=\"cover-image\""));
+    let first = opf.find("idref=\"page1\"").unwrap();
+    let second = opf.find("idref=\"page2\"").unwrap();
+    assert!(first < second);
+
+    let mut page = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("OEBPS/p0001.xhtml").unwrap(), &mut page)
+        .unwrap();
+    assert!(page.contains("width=30, height=50"));
+    assert!(page.contains("images/0001.png"));
+}
+
+#[test]
+fn test_pdf_embeds_pages_at_native_size() {
+    use mangabot_rs::models::MangaDetail;
+    use mangabot_rs::utils::archive::{ArchiveFormat, ArchiveSource, build, verify};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("manga");
+    fs::create_dir_all(&dir).unwrap();
+    let png = dir.join("1.png");
+    image::RgbImage::new(30, 50).save(&png).unwrap();
+    let jpg = dir.join("2.jpg");
+    image::RgbImage::new(40, 20).save(&jpg).unwrap();
+    let pages = vec![png.to_string_lossy().to_string(), jpg.to_string_lossy().to_string()];
+
+    let detail = MangaDetail { author: "author".to_string(), ..common::detail(1, "标题", 2) };
+    let src = ArchiveSource {
+        dir: dir.to_str().unwrap(),
+        pages: &pages,
+        detail: &detail,
+        source_url: "https://example.com/photos-index-aid-1.html",
+        cover: None,
+    };
+    let out = tmp.path().join("out.pdf");
+    let out = out.to_str().unwrap();
+    build(ArchiveFormat::Pdf, &src, out).unwrap();
+    assert!(verify(ArchiveFormat::Pdf, out, 2));
+    assert!(!verify(ArchiveFormat::Pdf, out, 3));
+
+    let bytes = fs::read(out).unwrap();
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(text.starts_with("%PDF-1.7"));
+    assert!(text.contains("/MediaBox [0 0 30 50]"));
+    assert!(text.contains("/MediaBox [0 0 40 20]"));
+    assert!(text.contains("/Filter /DCTDecode"));
+    assert!(text.contains("/Filter /FlateDecode"));
+    assert!(text.contains("/Title <FEFF68079898>"));
+    assert!(text.contains("/Author <FEFF0061007500740068006F0072>"));
+
+    // xref 中的偏移必须指向对应对象的开头
+    let startxref = bytes.windows(10).rposition(|w| w == b"startxref\n").unwrap();
+    let tail = std::str::from_utf8(&bytes[startxref + 10..]).unwrap();
+    let xref: usize = tail.lines().next().unwrap().parse().unwrap();
+    let table = std::str::from_utf8(&bytes[xref..]).unwrap();
+    let mut lines = table.lines().skip(2);
+    lines.next(); // 0 号空闲对象
+    for id in 1..=9 {
+        let offset: usize = lines.next().unwrap()[..10].parse().unwrap();
+        assert!(bytes[offset..].starts_with(format!("{} 0 obj", id).as_bytes()));
+    }
+}
+
+#[test]
+fn test_zip_volume_only_contains_its_pages() {
+    use mangabot_rs::utils::archive::{ArchiveFormat, ArchiveSource, build, verify};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().join("manga");
+    fs::create_dir_all(&dir).unwrap();
+    let pages: Vec<String> = ["1.jpg", "2.jpg", "3.jpg"]
+        .iter()
+        .map(|name| {
+            let path = dir.join(name);
+            fs::write(&path, name.as_bytes()).unwrap();
+            path.to_string_lossy().to_string()
+        })
+        .collect();
+
+    let detail = common::detail(1, "t", 3);
+    let src = ArchiveSource {
+        dir: dir.to_str().unwrap(),
+        pages: &pages[1..],
+        detail: &detail,
+        source_url: "",
+        cover: None,
+    };
+    let out = tmp.path().join("t (2of2).zip");
+    let out = out.to_str().unwrap();
+    build(ArchiveFormat::Zip, &src, out).unwrap();
+    assert!(verify(ArchiveFormat::Zip, out, 2));
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(out).unwrap()).unwrap();
+    assert!(archive.by_name("1.jpg").is_err());
+    assert!(archive.by_name("2.jpg").is_ok());
+    assert!(archive.by_name("3.jpg").is_ok());
+}
+
+#[test]
+fn test_zip_stream_readable_by_zip_reader() {
+    use mangabot_rs::utils::zip::ZipStream;
+
+    let mut zip = ZipStream::new();
+    let mut out = Vec::new();
+    for (name, chunks) in
+        [("0001.webp", vec![&b"ab"[..], &b"cd"[..]]), ("0002.jpg", vec![&b"xyz"[..]])]
+    {
+        out.extend(zip.start_entry(name).unwrap());
+        for chunk in chunks {
+            zip.update(chunk).unwrap();
+            out.extend_from_slice(chunk);
+        }
+        out.extend(zip.finish_entry().unwrap());
+    }
+    out.extend(zip.finish().unwrap());
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(out)).unwrap();
+    assert_eq!(archive.len(), 2);
+    let mut first = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("0001.webp").unwrap(), &mut first).unwrap();
+    assert_eq!(first, "abcd");
+    let mut second = String::new();
+    std::io::Read::read_to_string(&mut archive.by_index(1).unwrap(), &mut second).unwrap();
+    assert_eq!(second, "xyz");
+}
+
+#[test]
+fn test_zip_stream_rejects_more_entries_than_the_format_allows() {
+    use mangabot_rs::utils::zip::ZipStream;
+
+    let mut zip = ZipStream::new();
+    for i in 0..u16::MAX {
+        zip.start_entry(&i.to_string()).unwrap();
+        zip.finish_entry().unwrap();
+    }
+    // 不支持 zip64，条目数超出结束记录的 16 位字段时报错，而不是写出损坏的目录
+    assert!(zip.start_entry("overflow").is_err());
+    assert!(zip.finish().is_ok());
+}
+
+#[test]
+fn test_compress_folders_one_dir_per_work() {
+    use mangabot_rs::utils::zip::compress_folders;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let page = |name: &str| {
+        let path = tmp.path().join(name);
+        fs::write(&path, name.as_bytes()).unwrap();
+        path.to_string_lossy().to_string()
+    };
+    let folders = vec![
+        ("作品A".to_string(), vec![page("0003.jpg"), page("0007.png")]),
+        ("b/c".to_string(), vec![page("0001.webp")]),
+    ];
+    let out = tmp.path().join("merged.zip");
+    compress_folders(&folders, out.to_str().unwrap()).unwrap();
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(&out).unwrap()).unwrap();
+    assert!(archive.by_name("作品A/0001.jpg").is_ok());
+    assert!(archive.by_name("作品A/0002.png").is_ok());
+    assert!(archive.by_name("b_c/0001.webp").is_ok());
+    assert!(mangabot_rs::utils::zip::verify_archive(out.to_str().unwrap(), 3));
+}
@@ -0,0 +1,67 @@
+use actix_web::{App, test, web};
+use mangabot_rs::config::Config;
+use mangabot_rs::services::health;
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::utils::cache;
+use serde_json::Value;
+
+async fn get(cfg: Config, uri: &str) -> (u16, Value) {
+    let app =
+        test::init_service(App::new().app_data(web::Data::new(cfg)).configure(web_configure)).await;
+    let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+    let status = resp.status().as_u16();
+    (status, serde_json::from_slice(&test::read_body(resp).await).unwrap())
+}
+
+#[actix_web::test]
+async fn test_healthz_always_ok() {
+    let (status, body) = get(Config::load().unwrap(), "/healthz").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ok");
+}
+
+#[actix_web::test]
+async fn test_readyz_reports_each_check() {
+    let mut cfg = Config::load().unwrap();
+    // 不配置源站，避免就绪检查主动探测
+    cfg.manga.base_url = String::new();
+    let dir = tempfile::tempdir().unwrap();
+    cfg.server.download_path = dir.path().join("downloads").to_string_lossy().to_string();
+
+    let (status, body) = get(cfg.clone(), "/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["checks"]["caches"], false);
+    assert_eq!(body["checks"]["download_dir"], true);
+    assert_eq!(body["checks"]["dispatcher"], false);
+    assert_eq!(body["checks"]["upstream"], false);
+    assert!(body["last_parse"]["last_success"].is_null());
+
+    cache::init(&cfg).unwrap();
+    health::set_dispatcher_running(true);
+    health::record_parse(20);
+    let (status, body) = get(cfg.clone(), "/readyz").await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["last_parse"]["results"], 20);
+
+    // 源站改版后解析为空，不刷新成功时间
+    health::record_parse(0);
+    let (_, body) = get(cfg.clone(), "/readyz").await;
+    assert_eq!(body["last_parse"]["results"], 0);
+    assert_eq!(body["checks"]["upstream"], true);
+    assert!(body["last_parse"]["last_success"].is_i64());
+
+    let mut unwritable = cfg.clone();
+    let file = dir.path().join("file");
+    std::fs::write(&file, b"x").unwrap();
+    unwritable.server.download_path = file.to_string_lossy().to_string();
+    let (status, body) = get(unwritable, "/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["checks"]["download_dir"], false);
+
+    health::set_dispatcher_running(false);
+    let (status, body) = get(cfg, "/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["checks"]["dispatcher"], false);
+}
@@ -0,0 +1,26 @@
+use mangabot_rs::utils::{human_bytes, human_duration, parse_bytes};
+use std::time::Duration;
+
+#[test]
+fn test_human_bytes() {
+    assert_eq!(human_bytes(512), "512 B");
+    assert_eq!(human_bytes(1536), "1.5 KB");
+    assert_eq!(human_bytes(50 * 1024 * 1024), "50.0 MB");
+}
+
+#[test]
+fn test_human_duration() {
+    assert_eq!(human_duration(Duration::from_secs(42)), "42秒");
+    assert_eq!(human_duration(Duration::from_secs(125)), "2分5秒");
+    assert_eq!(human_duration(Duration::from_secs(3720)), "1时2分");
+}
+
+#[test]
+fn test_parse_bytes() {
+    assert_eq!(parse_bytes("512"), Some(512));
+    assert_eq!(parse_bytes("512K"), Some(512 * 1024));
+    assert_eq!(parse_bytes("1.5m"), Some(1536 * 1024));
+    assert_eq!(parse_bytes("2MB"), Some(2 * 1024 * 1024));
+    assert_eq!(parse_bytes("fast"), None);
+    assert_eq!(parse_bytes("-1K"), None);
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use actix_web::{App, test, web};
+use common::{Reply, encode_png, serve};
+use mangabot_rs::config::Config;
+use mangabot_rs::services::proxy::{self, ImageProxy, proxy_url};
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::utils::http::host_allowed;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::net::TcpListener;
+
+struct Upstream {
+    base: String,
+    hits: Arc<AtomicUsize>,
+}
+
+fn disk_size(dir: &std::path::Path) -> u64 {
+    std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().metadata().unwrap().len()).sum()
+}
+
+static UPSTREAM: OnceLock<Upstream> = OnceLock::new();
+
+/// 全局初始化一次：图片源、HTTP 客户端与 /img 使用的缓存目录；
+/// 各测试运行在各自的运行时上，图片源放在独立线程中
+fn upstream() -> &'static Upstream {
+    UPSTREAM.get_or_init(|| {
+        let mut cfg = Config::load().unwrap();
+        let dir = tempfile::tempdir().unwrap().keep();
+        cfg.server.image_cache_path = dir.to_string_lossy().to_string();
+        mangabot_rs::utils::client::init(&cfg).unwrap();
+        proxy::init(&cfg).unwrap();
+        mangabot_rs::services::links::init(&cfg).unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            // /img/N 返回 N×N 的 PNG，/html 返回 HTML，/huge 声明超大的长度但不发送内容
+            let route = move |path: &str| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let path = path.split('?').next().unwrap_or("");
+                match path.strip_prefix("/img/").and_then(|n| n.parse().ok()) {
+                    Some(size) => Reply::ok(encode_png(size)),
+                    None if path == "/html" => {
+                        Reply::ok(b"<!DOCTYPE html><html>blocked</html>".to_vec())
+                    }
+                    None if path == "/huge" => {
+                        Reply::ok(Vec::new()).length(Some(u32::MAX as usize))
+                    }
+                    None => Reply::ok(Vec::new()),
+                }
+            };
+            rt.block_on(
+                async move { serve(TcpListener::from_std(listener).unwrap(), route).await },
+            );
+        });
+        Upstream { base, hits }
+    })
+}
+
+#[tokio::test]
+async fn test_host_policy() {
+    let base = "https://www.example.com";
+    let extra = vec!["cdn.example.net".to_string()];
+    assert!(host_allowed("https://www.example.com/a.jpg", base, &extra));
+    assert!(host_allowed("https://cdn.example.net/a.jpg", base, &extra));
+    assert!(host_allowed("https://img1.cdn.example.net/a.jpg", base, &extra));
+    assert!(!host_allowed("https://evilcdn.example.net/a.jpg", base, &extra));
+    assert!(!host_allowed("https://127.0.0.1/a.jpg", base, &extra));
+    assert!(!host_allowed("file:///etc/passwd", base, &extra));
+    assert!(!host_allowed("not a url", base, &extra));
+    // 未配置 base_url 时与 fetch 一致，不做限制
+    assert!(host_allowed("https://anything.example/a.jpg", "", &[]));
+}
+
+#[tokio::test]
+async fn test_disk_cache_hits_and_lru_eviction() {
+    let up = upstream();
+    let dir = tempfile::tempdir().unwrap();
+    let dir_str = dir.path().to_str().unwrap();
+    let one = encode_png(8).len() as u64;
+    // 上限只够容纳两张图片
+    let cache = ImageProxy::open(dir_str, one * 2 + one / 2, &up.base, &[]).unwrap();
+
+    let url = |n: u32| format!("{}/img/8?{}", up.base, n);
+    let before = up.hits.load(Ordering::SeqCst);
+    let (key1, bytes) = cache.get(&url(1)).await.unwrap();
+    assert_eq!(bytes, encode_png(8));
+    cache.get(&url(1)).await.unwrap();
+    assert_eq!(up.hits.load(Ordering::SeqCst) - before, 1);
+
+    let (key2, _) = cache.get(&url(2)).await.unwrap();
+    // 访问 1 使 2 成为最久未用
+    cache.get(&url(1)).await.unwrap();
+    let (key3, _) = cache.get(&url(3)).await.unwrap();
+    assert!(dir.path().join(&key1).exists());
+    assert!(!dir.path().join(&key2).exists());
+    assert!(dir.path().join(&key3).exists());
+    assert_eq!(disk_size(dir.path()), one * 2);
+
+    // 重新打开时从磁盘恢复索引，命中缓存不再请求源站
+    let reopened = ImageProxy::open(dir_str, 0, &up.base, &[]).unwrap();
+    let hits = up.hits.load(Ordering::SeqCst);
+    assert_eq!(reopened.get(&url(3)).await.unwrap().0, key3);
+    assert_eq!(up.hits.load(Ordering::SeqCst), hits);
+
+    // 非图片内容不缓存，其他主机被拒绝
+    assert!(cache.get(&format!("{}/html", up.base)).await.is_err());
+    assert_eq!(disk_size(dir.path()), one * 2);
+    assert!(cache.get("http://other.invalid/img/8").await.is_err());
+    // 超出大小上限的响应不读入内存
+    assert!(cache.get(&format!("{}/huge", up.base)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_concurrent_misses_share_the_cache_entry() {
+    let up = upstream();
+    let dir = tempfile::tempdir().unwrap();
+    // 上次中断留下的临时文件在打开时清理
+    std::fs::write(dir.path().join("stale.part"), b"partial").unwrap();
+    let cache = ImageProxy::open(dir.path().to_str().unwrap(), 0, &up.base, &[]).unwrap();
+    assert!(!dir.path().join("stale.part").exists());
+
+    let url = format!("{}/img/12?concurrent", up.base);
+    let results = futures::future::join_all((0..4).map(|_| cache.get(&url))).await;
+    for result in results {
+        assert_eq!(result.unwrap().1, encode_png(12));
+    }
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[actix_web::test]
+async fn test_image_route_sets_cache_headers() {
+    let up = upstream();
+    let cfg = Config::load().unwrap();
+    let app =
+        test::init_service(App::new().app_data(web::Data::new(cfg)).configure(web_configure)).await;
+
+    let uri = proxy_url(&format!("{}/img/16", up.base));
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+    assert!(resp.headers().get("cache-control").unwrap().to_str().unwrap().contains("max-age"));
+    let etag = resp.headers().get("etag").unwrap().clone();
+    assert_eq!(test::read_body(resp).await.to_vec(), encode_png(16));
+
+    let req =
+        test::TestRequest::get().uri(&uri).insert_header(("If-None-Match", etag)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 304);
+
+    // 未签名或签名不符的地址一律拒绝，不会请求源站
+    let hits = up.hits.load(Ordering::SeqCst);
+    let unsigned =
+        format!("/img?url={}%2Fimg%2F20", up.base.replace(':', "%3A").replace('/', "%2F"));
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&unsigned).to_request()).await;
+    assert_eq!(resp.status().as_u16(), 400);
+    let forged = format!("{}&sig=AAAA", unsigned);
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&forged).to_request()).await;
+    assert_eq!(resp.status().as_u16(), 403);
+    assert_eq!(up.hits.load(Ordering::SeqCst), hits);
+
+    let uri = proxy_url(&format!("{}/html", up.base));
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status().as_u16(), 502);
+}
+
+#[tokio::test]
+async fn test_public_proxy_url_for_telegram() {
+    let up = upstream();
+    let src = format!("{}/img/8", up.base);
+    let mut cfg = Config::load().unwrap();
+
+    // Telegram 无法访问本机地址，保留原图地址
+    cfg.server.web_host = "http://localhost:8087".to_string();
+    assert_eq!(proxy::public_proxy_url(&cfg, &src), src);
+
+    cfg.server.web_host = "https://manga.example.com/".to_string();
+    let url = proxy::public_proxy_url(&cfg, &src);
+    assert_eq!(url, format!("https://manga.example.com{}", proxy_url(&src)));
+    assert!(url.contains("/img?url=") && url.contains("&sig="));
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{Reply, encode, pages, serve};
+use mangabot_rs::config::Config;
+use mangabot_rs::utils::http::download_pages;
+use mangabot_rs::utils::img::verify_image;
+use tokio::net::TcpListener;
+
+#[test]
+fn test_verify_image_formats() {
+    assert_eq!(verify_image(&encode(image::ImageFormat::Jpeg)).unwrap(), "jpg");
+    assert_eq!(verify_image(&encode(image::ImageFormat::Png)).unwrap(), "png");
+    assert_eq!(verify_image(&encode(image::ImageFormat::WebP)).unwrap(), "webp");
+    assert_eq!(verify_image(&encode(image::ImageFormat::Gif)).unwrap(), "gif");
+}
+
+#[test]
+fn test_verify_image_rejects_broken() {
+    assert!(verify_image(b"").is_err());
+    assert!(verify_image(&[0xFF, 0xD8, 0xFF, 0xE0]).is_err());
+
+    let html = b"<!DOCTYPE html><html><head><title>404</title></head><body>Not Found</body></html>";
+    let err = verify_image(html).unwrap_err().to_string();
+    assert!(err.contains("HTML"), "{}", err);
+
+    // 截断的文件：头部完整但缺少结束标记
+    for format in [image::ImageFormat::Jpeg, image::ImageFormat::Png, image::ImageFormat::WebP] {
+        let bytes = encode(format);
+        assert!(verify_image(&bytes[..bytes.len() - 20]).is_err(), "{:?}", format);
+    }
+
+    // 魔数正确但图片头损坏
+    let mut png = encode(image::ImageFormat::Png);
+    png[12..16].copy_from_slice(b"XXXX");
+    assert!(verify_image(&png).is_err());
+}
+
+#[tokio::test]
+async fn test_download_rejects_invalid_pages() {
+    mangabot_rs::utils::client::init(&Config::load().unwrap()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    // /ok 返回 PNG，/html 以 200 返回错误页，/short 不带长度返回半截 PNG
+    tokio::spawn(serve(listener, |path| {
+        let png = encode(image::ImageFormat::Png);
+        match path {
+            "/ok" => Reply::ok(png).content_type("image/png"),
+            "/html" => Reply::ok(b"<html>busy</html>".to_vec()).content_type("text/html"),
+            "/short" => {
+                Reply::ok(png[..png.len() - 20].to_vec()).content_type("image/png").length(None)
+            }
+            _ => Reply::ok(Vec::new()).content_type("text/plain"),
+        }
+    }));
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let urls = vec![format!("{}/ok", base), format!("{}/html", base), format!("{}/short", base)];
+    let report = download_pages(pages(&urls), urls.len(), dir, 3, None).await;
+
+    assert!(report.pages[0].is_ok());
+    assert!(!report.pages[1].is_ok());
+    assert!(!report.pages[2].is_ok());
+    assert_eq!(report.corrupted_pages(), vec![2, 3]);
+    // 损坏的页面重试过且没有留下文件
+    assert_eq!(report.pages[1].attempts, 3);
+    let files: Vec<_> = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(files.len(), 1);
+
+    // 已落盘但损坏的页面在下次下载时被替换
+    std::fs::write(&report.pages[0].file_path, b"\x89PNG\r\n\x1A\ntruncated").unwrap();
+    let again = download_pages(pages(&urls), urls.len(), dir, 3, None).await;
+    assert_eq!(again.pages[0].attempts, 1);
+    assert!(again.pages[0].is_ok());
+}
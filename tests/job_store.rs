@@ -0,0 +1,84 @@
+use mangabot_rs::services::jobs::{DownloadOptions, Enqueued, Job, JobStatus, JobStore};
+use mangabot_rs::utils::archive::ArchiveFormat;
+
+#[tokio::test]
+async fn test_job_store_survives_reopen() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+
+    let store = JobStore::open(dir).unwrap();
+    let job = Job::new(123, 42, 7, "title".to_string(), 10, DownloadOptions::default());
+    let id = job.id.clone();
+    store.insert_or_join(job).await.unwrap();
+    store
+        .update(&id, |j| {
+            j.status = JobStatus::Downloading;
+            j.done = 3;
+        })
+        .await
+        .unwrap();
+    drop(store);
+
+    let reopened = JobStore::open(dir).unwrap();
+    let jobs = reopened.list().await;
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].aid, 123);
+    assert_eq!(jobs[0].status, JobStatus::Downloading);
+    assert_eq!(jobs[0].done, 3);
+
+    reopened.remove(&id).await.unwrap();
+    assert!(JobStore::open(dir).unwrap().list().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_job_store_joins_same_aid() {
+    let tmp = tempfile::tempdir().unwrap();
+    let store = JobStore::open(tmp.path().to_str().unwrap()).unwrap();
+
+    let first = Job::new(123, 1, 10, "title".to_string(), 10, DownloadOptions::default());
+    let first_id = first.id.clone();
+    assert!(matches!(store.insert_or_join(first).await.unwrap(), Enqueued::Created(_)));
+
+    let second = Job::new(123, 2, 20, "title".to_string(), 10, DownloadOptions::default());
+    match store.insert_or_join(second).await.unwrap() {
+        Enqueued::Joined(job) => {
+            assert_eq!(job.id, first_id);
+            let chats: Vec<i64> = job.subscribers().iter().map(|s| s.chat_id).collect();
+            assert_eq!(chats, vec![1, 2]);
+        }
+        Enqueued::Created(_) => panic!("same aid should join the running job"),
+    }
+    assert_eq!(store.list().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_job_store_does_not_join_other_format() {
+    let tmp = tempfile::tempdir().unwrap();
+    let store = JobStore::open(tmp.path().to_str().unwrap()).unwrap();
+
+    let zip = Job::new(123, 1, 10, "title".to_string(), 10, DownloadOptions::default());
+    store.insert_or_join(zip).await.unwrap();
+
+    let options = DownloadOptions { format: ArchiveFormat::Cbz, ..Default::default() };
+    let cbz = Job::new(123, 2, 20, "title".to_string(), 10, options);
+    assert!(matches!(store.insert_or_join(cbz).await.unwrap(), Enqueued::Created(_)));
+    assert_eq!(store.list().await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_job_store_does_not_join_other_pages_or_batch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let store = JobStore::open(tmp.path().to_str().unwrap()).unwrap();
+
+    let whole = Job::new(123, 1, 10, "title".to_string(), 10, DownloadOptions::default());
+    store.insert_or_join(whole).await.unwrap();
+
+    let options = DownloadOptions { pages: Some("1-3".to_string()), ..Default::default() };
+    let subset = Job::new(123, 2, 20, "title".to_string(), 3, options);
+    assert!(matches!(store.insert_or_join(subset).await.unwrap(), Enqueued::Created(_)));
+
+    let mut batch = Job::new(123, 3, 30, "title 等2部".to_string(), 20, Default::default());
+    batch.works = vec![123, 456];
+    assert!(matches!(store.insert_or_join(batch).await.unwrap(), Enqueued::Created(_)));
+    assert_eq!(store.list().await.len(), 3);
+}
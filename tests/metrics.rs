@@ -0,0 +1,52 @@
+use actix_web::{App, test, web};
+use mangabot_rs::config::Config;
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::utils::{cache, metrics};
+use std::time::Duration;
+
+async fn scrape(cfg: Config) -> String {
+    let app =
+        test::init_service(App::new().app_data(web::Data::new(cfg)).configure(web_configure)).await;
+    let resp =
+        test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    assert!(
+        resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/plain")
+    );
+    String::from_utf8(test::read_body(resp).await.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn test_metrics_endpoint_exports_counters_and_state() {
+    let mut cfg = Config::load().unwrap();
+    cache::init(&cfg).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.zip"), vec![0u8; 1000]).unwrap();
+    std::fs::create_dir(dir.path().join("work")).unwrap();
+    std::fs::write(dir.path().join("work").join("001.jpg"), vec![0u8; 234]).unwrap();
+    cfg.server.download_path = dir.path().to_string_lossy().to_string();
+
+    metrics::record_command("rank", true, Duration::from_millis(120));
+    metrics::record_command("zip", false, Duration::from_millis(30));
+    metrics::record_upstream(Some(200), Duration::from_millis(80));
+    metrics::record_upstream(None, Duration::from_millis(10));
+    metrics::metrics().image_bytes.inc_by(4096);
+
+    cache::image_cache().insert("1".to_string(), vec!["u".to_string()]).await;
+    assert!(cache::lookup(cache::image_cache(), "1").await.is_some());
+    assert!(cache::lookup(cache::image_cache(), "2").await.is_none());
+    cache::image_cache().run_pending_tasks().await;
+
+    let body = scrape(cfg).await;
+    assert!(body.contains("mangabot_commands_total{command=\"rank\",result=\"ok\"} 1"));
+    assert!(body.contains("mangabot_commands_total{command=\"zip\",result=\"error\"} 1"));
+    assert!(body.contains("mangabot_command_duration_seconds_count{command=\"rank\"} 1"));
+    assert!(body.contains("mangabot_upstream_requests_total{status=\"200\"} 1"));
+    assert!(body.contains("mangabot_upstream_requests_total{status=\"error\"} 1"));
+    assert!(body.contains("mangabot_upstream_request_duration_seconds_count 2"));
+    assert!(body.contains("mangabot_image_download_bytes_total 4096"));
+    assert!(body.contains("mangabot_cache_lookups_total{cache=\"image\",result=\"hit\"} 1"));
+    assert!(body.contains("mangabot_cache_lookups_total{cache=\"image\",result=\"miss\"} 1"));
+    assert!(body.contains("mangabot_cache_entries{cache=\"image\"} 1"));
+    assert!(body.contains("mangabot_download_dir_bytes 1234"));
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use actix_web::{App, test, web};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use common::{Reply, encode_png, serve};
+use mangabot_rs::config::Config;
+use mangabot_rs::models::MangaInfo;
+use mangabot_rs::services::opds::acquisition_feed;
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::utils::cache;
+use std::io::Read;
+use std::sync::OnceLock;
+
+static INIT: OnceLock<()> = OnceLock::new();
+
+fn setup() -> Config {
+    let mut cfg = Config::load().unwrap();
+    INIT.get_or_init(|| {
+        cache::init(&cfg).unwrap();
+        mangabot_rs::utils::client::init(&cfg).unwrap();
+        mangabot_rs::services::links::init(&cfg).unwrap();
+        // 图片代理只允许 img.example
+        let mut proxy_cfg = cfg.clone();
+        proxy_cfg.manga.base_url = "https://img.example".to_string();
+        proxy_cfg.server.image_cache_path =
+            tempfile::tempdir().unwrap().keep().to_string_lossy().to_string();
+        mangabot_rs::services::proxy::init(&proxy_cfg).unwrap();
+    });
+    cfg.server.api_keys = vec!["reader-key".to_string()];
+    cfg
+}
+
+fn basic(password: &str) -> (&'static str, String) {
+    ("Authorization", format!("Basic {}", STANDARD.encode(format!("koreader:{}", password))))
+}
+
+async fn get(cfg: &Config, uri: &str, auth: Option<(&str, String)>) -> (u16, String, Vec<u8>) {
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(cfg.clone())).configure(web_configure),
+    )
+    .await;
+    let mut req = test::TestRequest::get().uri(uri);
+    if let Some(header) = auth {
+        req = req.insert_header(header);
+    }
+    let resp = test::call_service(&app, req.to_request()).await;
+    let status = resp.status().as_u16();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .map(|v| v.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    (status, content_type, test::read_body(resp).await.to_vec())
+}
+
+#[actix_web::test]
+async fn test_opds_requires_basic_auth() {
+    let cfg = setup();
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(cfg.clone())).configure(web_configure),
+    )
+    .await;
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/opds").to_request()).await;
+    assert_eq!(resp.status().as_u16(), 401);
+    assert!(resp.headers().get("www-authenticate").unwrap().to_str().unwrap().starts_with("Basic"));
+
+    assert_eq!(get(&cfg, "/opds", Some(basic("wrong"))).await.0, 401);
+    assert_eq!(get(&cfg, "/opds", Some(basic("reader-key"))).await.0, 200);
+}
+
+#[actix_web::test]
+async fn test_opds_navigation_feeds() {
+    let cfg = setup();
+    let auth = || Some(basic("reader-key"));
+
+    let (status, content_type, body) = get(&cfg, "/opds", auth()).await;
+    assert_eq!(status, 200);
+    assert!(content_type.contains("kind=navigation"));
+    let body = String::from_utf8(body).unwrap();
+    assert!(body.contains("href=\"/opds/rank\""));
+    assert!(body.contains("href=\"/opds/cate\""));
+    assert!(body.contains("rel=\"search\" href=\"/opds/opensearch.xml\""));
+
+    let body = String::from_utf8(get(&cfg, "/opds/rank", auth()).await.2).unwrap();
+    for period in ["day", "week", "month"] {
+        assert!(body.contains(&format!("href=\"/opds/rank/{}\"", period)));
+    }
+
+    let body = String::from_utf8(get(&cfg, "/opds/cate", auth()).await.2).unwrap();
+    assert!(body.contains("href=\"/opds/cate/trz/zh\""));
+    assert!(body.contains("同人志-汉化"));
+    assert!(body.contains("href=\"/opds/cate/hm/src\""));
+    assert!(body.contains("韩漫-生肉"));
+
+    let (status, content_type, body) = get(&cfg, "/opds/opensearch.xml", auth()).await;
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/opensearchdescription+xml");
+    assert!(String::from_utf8(body).unwrap().contains("/opds/search?q={searchTerms}"));
+
+    assert_eq!(get(&cfg, "/opds/search", auth()).await.0, 400);
+}
+
+#[actix_web::test]
+async fn test_acquisition_feed_entries() {
+    setup();
+    let manga = MangaInfo {
+        id: 42,
+        rank: 1,
+        title: "A & B <篇>".to_string(),
+        cover: "https://img.example/42.jpg".to_string(),
+        author: "作者".to_string(),
+        total: 24,
+        fav: 0,
+        published: String::new(),
+    };
+    let other_host = MangaInfo {
+        id: 43,
+        cover: "https://cdn.other.example/43.jpg".to_string(),
+        ..manga.clone()
+    };
+    let xml = acquisition_feed(
+        "urn:test",
+        "列表",
+        "/opds/rank/day?page=1",
+        Some("/opds/rank/day?page=2"),
+        &[manga, other_host],
+    );
+    assert!(xml.contains("<id>urn:mangabot:42</id>"));
+    assert!(xml.contains("<title>A &amp; B &lt;篇&gt;</title>"));
+    assert!(xml.contains("rel=\"next\" href=\"/opds/rank/day?page=2\""));
+    assert!(xml.contains("href=\"/opds/download/42\" type=\"application/zip\""));
+    assert!(xml.contains("/img?url=https%3A%2F%2Fimg%2Eexample%2F42%2Ejpg&amp;sig="));
+    // 不在代理允许列表中的封面直接引用原地址
+    assert!(xml.contains("href=\"https://cdn.other.example/43.jpg\""));
+    assert!(xml.contains("<author><name>作者</name></author>"));
+}
+
+#[actix_web::test]
+async fn test_opds_download_streams_archive() {
+    let cfg = setup();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(serve(listener, |_| Reply::ok(encode_png(8))));
+
+    cache::info_cache().insert("8001".to_string(), common::detail(8001, "离线作品", 2)).await;
+    let images = vec![format!("{}/1.png", base), format!("{}/2.png", base)];
+    cache::image_cache().insert("8001".to_string(), images).await;
+
+    let (status, content_type, body) =
+        get(&cfg, "/opds/download/8001", Some(basic("reader-key"))).await;
+    assert_eq!(status, 200);
+    assert_eq!(content_type, "application/zip");
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body)).unwrap();
+    assert_eq!(archive.len(), 2);
+    let mut page = Vec::new();
+    archive.by_index(0).unwrap().read_to_end(&mut page).unwrap();
+    assert_eq!(page, encode_png(8));
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{Reply, encode, pages, serve};
+use mangabot_rs::config::Config;
+use mangabot_rs::utils::http::{ManifestPage, download_pages, page_extension, page_stem};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn test_pages_named_by_index_with_sniffed_extension() {
+    mangabot_rs::utils::client::init(&Config::load().unwrap()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(serve(listener, |path| match path {
+        // 魔数优先于 Content-Type 与 url 扩展名
+        "/z.webp?v=1" => Reply::ok(encode(image::ImageFormat::Jpeg)).content_type("image/webp"),
+        "/a" => {
+            Reply::ok(encode(image::ImageFormat::Png)).content_type("image/png; charset=binary")
+        }
+        _ => Reply::not_found(),
+    }));
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().to_str().unwrap();
+    let urls = vec![format!("{}/z.webp?v=1", base), format!("{}/a", base), format!("{}/x", base)];
+    let report = download_pages(pages(&urls), urls.len(), dir, 2, None).await;
+
+    assert_eq!(report.pages[0].file_path, format!("{}.jpg", page_stem(dir, 0, 3)));
+    assert!(report.pages[0].file_path.ends_with("/0001.jpg"));
+    assert!(report.pages[1].file_path.ends_with("/0002.png"));
+    assert!(!report.pages[2].is_ok());
+
+    let manifest = report.manifest();
+    assert_eq!(
+        manifest[0],
+        ManifestPage {
+            page: 1,
+            url: urls[0].clone(),
+            file: Some("0001.jpg".to_string()),
+            bytes: encode(image::ImageFormat::Jpeg).len() as u64
+        }
+    );
+    assert_eq!(manifest[2].file, None);
+
+    // 再次下载时复用已落盘的页面
+    let again = download_pages(pages(&urls), urls.len(), dir, 2, None).await;
+    assert_eq!(again.pages[1].attempts, 0);
+}
+
+#[test]
+fn test_page_extension_fallback() {
+    // 无法识别魔数时依次参考 Content-Type 与 url 扩展名
+    assert_eq!(page_extension(b"??", Some("image/png; charset=binary"), "http://h/a.jpg"), "png");
+    assert_eq!(page_extension(b"??", None, "http://h/a.JPEG?v=1"), "jpg");
+    assert_eq!(page_extension(b"??", Some("application/octet-stream"), "http://h/a"), "bin");
+}
+
+#[test]
+fn test_page_stem_width() {
+    assert_eq!(page_stem("d", 0, 10), "d/0001");
+    assert_eq!(page_stem("d", 11999, 12000), "d/12000");
+}
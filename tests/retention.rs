@@ -0,0 +1,144 @@
+use mangabot_rs::services::retention::{
+    EvictReason, RetentionPolicy, WorkUsage, plan, scan, work_key,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+fn work(title: &str, bytes: u64, hours_ago: u64, now: SystemTime) -> WorkUsage {
+    WorkUsage {
+        title: title.to_string(),
+        paths: vec![],
+        bytes,
+        modified: now - Duration::from_secs(hours_ago * 3600),
+    }
+}
+
+#[test]
+fn test_work_key_groups_archives_and_volumes() {
+    assert_eq!(work_key("标题", true).as_deref(), Some("标题"));
+    assert_eq!(work_key("标题.zip", false).as_deref(), Some("标题"));
+    assert_eq!(work_key("标题 [jpeg] (2of3).cbz", false).as_deref(), Some("标题"));
+    assert_eq!(work_key("标题 [png] [p1-20,35].zip", false).as_deref(), Some("标题"));
+    assert_eq!(work_key("a [b].pdf", false).as_deref(), Some("a [b]"));
+    assert_eq!(work_key(".jobs.json", false), None);
+    assert_eq!(work_key("notes.txt", false), None);
+}
+
+#[test]
+fn test_touch_marks_reused_archive_as_recent() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("标题.zip");
+    fs::write(&archive, b"zip").unwrap();
+    let old = SystemTime::now() - Duration::from_secs(30 * 24 * 3600);
+    fs::File::options().write(true).open(&archive).unwrap().set_modified(old).unwrap();
+    assert_eq!(scan(dir.path()).unwrap()[0].modified, old);
+
+    mangabot_rs::utils::fs::touch(&archive).unwrap();
+    assert!(scan(dir.path()).unwrap()[0].modified > old + Duration::from_secs(3600));
+}
+
+#[test]
+fn test_plan_age_count_and_quota() {
+    let now = SystemTime::now();
+    let works = vec![
+        work("new", 10, 1, now),
+        work("mid", 10, 5, now),
+        work("old", 10, 10, now),
+        work("ancient", 10, 100, now),
+    ];
+    let none = HashSet::new();
+
+    let policy =
+        RetentionPolicy { max_age: Some(Duration::from_secs(50 * 3600)), ..Default::default() };
+    assert_eq!(plan(&works, &policy, &none, now), vec![(3, EvictReason::Expired)]);
+
+    let policy = RetentionPolicy { keep_recent: 2, ..Default::default() };
+    assert_eq!(
+        plan(&works, &policy, &none, now),
+        vec![(2, EvictReason::OverCount), (3, EvictReason::OverCount)]
+    );
+
+    // 超出容量时从最久未下载的开始清理，受保护的作品跳过
+    let policy = RetentionPolicy { max_bytes: 25, ..Default::default() };
+    let protected: HashSet<String> = ["ancient".to_string()].into();
+    assert_eq!(
+        plan(&works, &policy, &protected, now),
+        vec![(2, EvictReason::OverQuota), (1, EvictReason::OverQuota)]
+    );
+}
+
+#[test]
+fn test_scan_sums_work_entries() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::create_dir_all(tmp.path().join("t/jpeg")).unwrap();
+    fs::write(tmp.path().join("t/0001.webp"), [0u8; 10]).unwrap();
+    fs::write(tmp.path().join("t/jpeg/0001.jpg"), [0u8; 5]).unwrap();
+    fs::write(tmp.path().join("t (1of2).zip"), [0u8; 3]).unwrap();
+    fs::write(tmp.path().join(".jobs.json"), b"[]").unwrap();
+
+    let works = scan(tmp.path()).unwrap();
+    assert_eq!(works.len(), 1);
+    assert_eq!(works[0].title, "t");
+    assert_eq!(works[0].bytes, 18);
+    assert_eq!(works[0].paths.len(), 2);
+}
+
+#[tokio::test]
+async fn test_run_once_keeps_archives_with_unexpired_links() {
+    use mangabot_rs::config::Config;
+    use mangabot_rs::services::{jobs, links, retention};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut cfg = Config::load().unwrap();
+    cfg.server.download_path = dir.path().to_string_lossy().to_string();
+    cfg.server.link_secret = "secret".to_string();
+    jobs::init(&cfg).unwrap();
+    links::init(&cfg).unwrap();
+
+    let signed = dir.path().join("已发链接.zip");
+    fs::write(&signed, vec![0u8; 100]).unwrap();
+    fs::write(dir.path().join("无链接.zip"), vec![0u8; 100]).unwrap();
+    links::sign_file(&cfg, signed.to_str().unwrap()).await.unwrap();
+
+    // 合并打包的任务按各作品目录及合并后的打包文件保护
+    let merged = dir.path().join("作品甲");
+    fs::create_dir(&merged).unwrap();
+    fs::write(merged.join("001.jpg"), vec![0u8; 50]).unwrap();
+    let combined = dir.path().join("作品甲 等2部.zip");
+    fs::write(&combined, vec![0u8; 50]).unwrap();
+    let mut job = jobs::Job::new(1, 1, 1, "作品甲 等2部".to_string(), 2, Default::default());
+    job.works = vec![1, 2];
+    job.work_titles = vec!["作品甲".to_string(), "作品乙".to_string()];
+    jobs::store().insert_or_join(job).await.unwrap();
+
+    let policy = RetentionPolicy { max_bytes: 1, ..Default::default() };
+    assert_eq!(retention::run_once(&cfg, &policy).await.unwrap(), 100);
+    assert!(signed.exists());
+    assert!(merged.join("001.jpg").exists());
+    assert!(combined.exists());
+    assert!(!dir.path().join("无链接.zip").exists());
+
+    // 登记的链接在重启后仍然有效
+    let reopened = links::LinkStore::open(&cfg.server.download_path, "secret").unwrap();
+    assert_eq!(reopened.active_paths().await, vec!["已发链接.zip".to_string()]);
+}
+
+#[tokio::test]
+async fn test_link_store_reads_legacy_counter_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let exp = chrono::Utc::now().timestamp() + 3600;
+    let legacy = format!(r#"{{"sig":{{"exp":{},"count":1}}}}"#, exp);
+    fs::write(dir.path().join(".links.json"), legacy).unwrap();
+
+    let store =
+        mangabot_rs::services::links::LinkStore::open(dir.path().to_str().unwrap(), "s").unwrap();
+    let link = mangabot_rs::services::links::SignedLink {
+        path: "a.zip".to_string(),
+        exp,
+        max: 1,
+        sig: "sig".to_string(),
+    };
+    assert!(!store.record(&link).await.unwrap());
+    assert!(store.active_paths().await.is_empty());
+}
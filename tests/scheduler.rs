@@ -0,0 +1,64 @@
+use mangabot_rs::services::scheduler::{Position, Scheduler};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_scheduler_caps_running_jobs() {
+    let scheduler = Scheduler::new(1);
+    let first = scheduler.enqueue("a", 1).wait().await;
+    let second = scheduler.enqueue("b", 2);
+    assert_eq!(scheduler.position("a"), Some(Position::Running));
+    assert_eq!(scheduler.position("b"), Some(Position::Queued(1)));
+
+    let waiting = tokio::time::timeout(Duration::from_millis(50), second.wait()).await;
+    assert!(waiting.is_err());
+    // 等待超时丢弃了凭据，任务应已出队
+    assert_eq!(scheduler.position("b"), None);
+
+    let third = scheduler.enqueue("c", 2);
+    drop(first);
+    let _running = third.wait().await;
+    assert_eq!(scheduler.position("c"), Some(Position::Running));
+}
+
+#[tokio::test]
+async fn test_scheduler_rotates_between_users() {
+    let scheduler = Scheduler::new(1);
+    let running = scheduler.enqueue("u1-a", 1).wait().await;
+
+    // 用户 1 连续提交多个任务，用户 2 后到也不必排在其后
+    let _t1 = scheduler.enqueue("u1-b", 1);
+    let _t2 = scheduler.enqueue("u1-c", 1);
+    let _t3 = scheduler.enqueue("u2-a", 2);
+    assert_eq!(scheduler.position("u2-a"), Some(Position::Queued(1)));
+    assert_eq!(scheduler.position("u1-b"), Some(Position::Queued(2)));
+    assert_eq!(scheduler.position("u1-c"), Some(Position::Queued(3)));
+    drop(running);
+}
+
+#[tokio::test]
+async fn test_scheduler_wakes_waiters_when_a_job_starts() {
+    let scheduler = Scheduler::new(2);
+    let first = scheduler.enqueue("b", 1);
+    let second = scheduler.enqueue("a", 2);
+
+    // "a" 先开始等待，此时排在 "b" 之后还不能启动
+    let waiting = second.wait();
+    tokio::pin!(waiting);
+    assert!(futures::poll!(&mut waiting).is_pending());
+
+    // "b" 启动后仍有空闲名额，"a" 应被唤醒
+    let _b = first.wait().await;
+    let _a = tokio::time::timeout(Duration::from_millis(200), waiting).await.expect("a started");
+    assert_eq!(scheduler.position("a"), Some(Position::Running));
+}
+
+#[test]
+fn test_job_owner_is_requesting_user() {
+    use mangabot_rs::services::jobs::Job;
+
+    let mut job = Job::new(1, -100, 1, "title".to_string(), 1, Default::default());
+    // 旧版本的任务日志没有记录用户，按会话区分
+    assert_eq!(job.owner(), -100);
+    job.user_id = 42;
+    assert_eq!(job.owner(), 42);
+}
@@ -0,0 +1,43 @@
+use mangabot_rs::config::Config;
+use mangabot_rs::error::BotError;
+use mangabot_rs::services::{shutdown, web};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_drain_waits_then_interrupts_at_deadline() {
+    assert!(shutdown::drain(Duration::from_secs(1)).await);
+
+    let guard = shutdown::track();
+    let quick = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(guard);
+    });
+    assert!(shutdown::drain(Duration::from_secs(5)).await);
+    quick.await.unwrap();
+    assert_eq!(shutdown::running(), 0);
+
+    shutdown::trigger();
+    assert!(shutdown::token().is_cancelled());
+    let guard = shutdown::track();
+    let stuck = tokio::spawn(async move {
+        shutdown::deadline().cancelled().await;
+        drop(guard);
+    });
+    let started = Instant::now();
+    assert!(!shutdown::drain(Duration::from_millis(100)).await);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    stuck.await.unwrap();
+    assert_eq!(shutdown::running(), 0);
+}
+
+#[tokio::test]
+async fn test_web_start_reports_bind_failure() {
+    let taken = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+    let mut cfg = Config::load().unwrap();
+    cfg.server.port = taken.local_addr().unwrap().port();
+    assert!(matches!(web::start(cfg.clone()), Err(BotError::Io(_))));
+
+    drop(taken);
+    let handle = web::start(cfg).unwrap();
+    handle.stop(true).await;
+}
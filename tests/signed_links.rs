@@ -0,0 +1,81 @@
+use actix_web::{App, test, web};
+use mangabot_rs::config::Config;
+use mangabot_rs::services::links::{self, SignedLink};
+use mangabot_rs::services::web::configure as web_configure;
+use std::sync::OnceLock;
+
+static INIT: OnceLock<()> = OnceLock::new();
+
+fn setup() -> (Config, tempfile::TempDir) {
+    let mut cfg = Config::load().unwrap();
+    INIT.get_or_init(|| {
+        mangabot_rs::utils::cache::init(&cfg).unwrap();
+        links::init(&cfg).unwrap();
+    });
+    let dir = tempfile::tempdir().unwrap();
+    cfg.server.download_path = dir.path().to_string_lossy().to_string();
+    std::fs::write(dir.path().join("作品 A.zip"), b"hello").unwrap();
+    (cfg, dir)
+}
+
+async fn get(cfg: &Config, link: &SignedLink) -> u16 {
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(cfg.clone())).configure(web_configure),
+    )
+    .await;
+    let req = test::TestRequest::get().uri(&format!("/download?{}", link.query())).to_request();
+    test::call_service(&app, req).await.status().as_u16()
+}
+
+#[actix_web::test]
+async fn test_signature_covers_all_fields() {
+    let link = SignedLink::new(b"secret", "a.zip", 100, 0);
+    assert!(link.verify(b"secret"));
+    assert!(!link.verify(b"other"));
+    assert!(!SignedLink { path: "b.zip".to_string(), ..link.clone() }.verify(b"secret"));
+    assert!(!SignedLink { exp: 200, ..link.clone() }.verify(b"secret"));
+    assert!(!SignedLink { max: 1, ..link.clone() }.verify(b"secret"));
+    assert!(link.is_expired(100));
+    assert!(!link.is_expired(99));
+}
+
+#[actix_web::test]
+async fn test_signed_link_download() {
+    let (cfg, dir) = setup();
+    let file = dir.path().join("作品 A.zip");
+    let link = links::sign_file(&cfg, file.to_str().unwrap()).await.unwrap();
+    assert_eq!(link.path, "作品 A.zip");
+    assert!(links::download_url(&cfg, &link).contains("/download?path="));
+    assert_eq!(get(&cfg, &link).await, 200);
+
+    let tampered = SignedLink { path: "../secret.zip".to_string(), ..link.clone() };
+    assert_eq!(get(&cfg, &tampered).await, 403);
+}
+
+#[actix_web::test]
+async fn test_signed_link_expired_or_escaping() {
+    let (cfg, _dir) = setup();
+    let secret = links::store().secret();
+    let now = chrono::Utc::now().timestamp();
+
+    let expired = SignedLink::new(secret, "作品 A.zip", now - 1, 0);
+    assert_eq!(get(&cfg, &expired).await, 410);
+
+    // 签名有效但越出下载目录
+    let outside = SignedLink::new(secret, "../../etc/passwd", now + 60, 0);
+    assert_eq!(get(&cfg, &outside).await, 404);
+}
+
+#[actix_web::test]
+async fn test_signed_link_download_limit() {
+    let (cfg, _dir) = setup();
+    let exp = chrono::Utc::now().timestamp() + 60;
+    let once = SignedLink::new(links::store().secret(), "作品 A.zip", exp, 1);
+    assert_eq!(get(&cfg, &once).await, 200);
+    assert_eq!(get(&cfg, &once).await, 410);
+
+    // 文件不存在时不消耗次数
+    let missing = SignedLink::new(links::store().secret(), "missing.zip", exp, 1);
+    assert_eq!(get(&cfg, &missing).await, 404);
+    assert!(links::store().record(&missing).await.unwrap());
+}
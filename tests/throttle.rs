@@ -0,0 +1,29 @@
+use mangabot_rs::utils::throttle::RateLimiter;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_rate_limiter_paces_bytes() {
+    let limiter = RateLimiter::with_rate(100 * 1024);
+    let start = Instant::now();
+    // 从空桶开始，50KB 需要约 0.5 秒
+    for _ in 0..10 {
+        limiter.acquire(5 * 1024).await;
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(1500), "{:?}", elapsed);
+}
+
+#[tokio::test]
+async fn test_rate_limiter_adjustable() {
+    let limiter = RateLimiter::with_rate(0);
+    let start = Instant::now();
+    limiter.acquire(10 * 1024 * 1024).await;
+    assert!(start.elapsed() < Duration::from_millis(50));
+
+    limiter.set_rate(1024);
+    assert_eq!(limiter.rate(), 1024);
+    let start = Instant::now();
+    limiter.acquire(256).await;
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
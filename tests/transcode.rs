@@ -0,0 +1,33 @@
+use mangabot_rs::utils::img::{Transcode, sniff_extension, transcode, transcoded_path};
+use std::fs;
+
+#[test]
+fn test_transcode_webp_to_jpeg_and_png() {
+    let tmp = tempfile::tempdir().unwrap();
+    let page = tmp.path().join("00001.webp");
+    image::RgbaImage::new(8, 6).save(&page).unwrap();
+    let page = page.to_str().unwrap();
+    assert_eq!(sniff_extension(&fs::read(page).unwrap()), Some("webp"));
+
+    let jpeg = transcode(page, Transcode::Jpeg, 80).unwrap();
+    assert_eq!(jpeg, transcoded_path(page, Transcode::Jpeg));
+    assert!(jpeg.ends_with("jpeg/00001.jpg"));
+    assert_eq!(sniff_extension(&fs::read(&jpeg).unwrap()), Some("jpg"));
+
+    let png = transcode(page, Transcode::Png, 80).unwrap();
+    assert!(png.ends_with("png/00001.png"));
+    assert_eq!(image::image_dimensions(&png).unwrap(), (8, 6));
+
+    // 原图保留，已是目标格式时直接复用
+    assert!(fs::metadata(page).is_ok());
+    assert_eq!(transcode(&png, Transcode::Png, 80).unwrap(), png);
+    assert_eq!(transcode(page, Transcode::Original, 80).unwrap(), page);
+}
+
+#[test]
+fn test_transcode_parse() {
+    assert_eq!(Transcode::parse("JPG"), Some(Transcode::Jpeg));
+    assert_eq!(Transcode::parse("png"), Some(Transcode::Png));
+    assert_eq!(Transcode::parse("original"), Some(Transcode::Original));
+    assert_eq!(Transcode::parse("bmp"), None);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use actix_web::{App, test, web};
+use mangabot_rs::config::Config;
+use mangabot_rs::models::MangaDetail;
+use mangabot_rs::services::jobs::{self, DownloadOptions, Job};
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::utils::cache;
+use serde_json::Value;
+use tokio::sync::OnceCell;
+
+static INIT: OnceCell<()> = OnceCell::const_new();
+
+async fn setup() -> Config {
+    let mut cfg = Config::load().unwrap();
+    INIT.get_or_init(|| async {
+        let mut cfg = cfg.clone();
+        cfg.server.download_path =
+            tempfile::tempdir().unwrap().keep().to_string_lossy().to_string();
+        cache::init(&cfg).unwrap();
+        jobs::init(&cfg).unwrap();
+        let job = Job::new(7001, 42, 1, "任务".to_string(), 3, DownloadOptions::default());
+        jobs::store().insert_or_join(job).await.unwrap();
+
+        let detail = MangaDetail {
+            cover: "https://img.example/cover.jpg".to_string(),
+            author: "作者".to_string(),
+            category: "同人".to_string(),
+            tags: vec!["标签".to_string()],
+            description: "简介".to_string(),
+            ..common::detail(7001, "作品", 2)
+        };
+        cache::info_cache().insert("7001".to_string(), detail).await;
+        let images = vec![
+            "https://img.example/1.webp".to_string(),
+            "https://img.example/2.webp".to_string(),
+        ];
+        cache::image_cache().insert("7001".to_string(), images).await;
+    })
+    .await;
+    cfg.server.api_keys = vec!["secret-key".to_string()];
+    cfg
+}
+
+async fn get(cfg: Config, uri: &str, key: Option<(&str, &str)>) -> (u16, Value) {
+    let app =
+        test::init_service(App::new().app_data(web::Data::new(cfg)).configure(web_configure)).await;
+    let mut req = test::TestRequest::get().uri(uri);
+    if let Some(header) = key {
+        req = req.insert_header(header);
+    }
+    let resp = test::call_service(&app, req.to_request()).await;
+    let status = resp.status().as_u16();
+    let body = test::read_body(resp).await;
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[actix_web::test]
+async fn test_api_requires_key() {
+    let cfg = setup().await;
+    assert_eq!(get(cfg.clone(), "/api/info/7001", None).await.0, 401);
+    assert_eq!(get(cfg.clone(), "/api/info/7001", Some(("X-Api-Key", "wrong"))).await.0, 401);
+    assert_eq!(
+        get(cfg.clone(), "/api/info/7001", Some(("Authorization", "Bearer secret-key"))).await.0,
+        200
+    );
+    // 密钥前缀不能通过校验，配置多个密钥时任一匹配即可
+    assert_eq!(get(cfg.clone(), "/api/info/7001", Some(("X-Api-Key", "secret"))).await.0, 401);
+    let mut multi = cfg.clone();
+    multi.server.api_keys.insert(0, "other-key".to_string());
+    assert_eq!(get(multi, "/api/info/7001", Some(("X-Api-Key", "secret-key"))).await.0, 200);
+
+    // 未配置密钥时接口关闭
+    let mut closed = cfg;
+    closed.server.api_keys.clear();
+    assert_eq!(get(closed, "/api/info/7001", Some(("X-Api-Key", "secret-key"))).await.0, 404);
+}
+
+#[actix_web::test]
+async fn test_api_info_and_images() {
+    let cfg = setup().await;
+    let key = Some(("X-Api-Key", "secret-key"));
+
+    let (status, detail) = get(cfg.clone(), "/api/info/7001", key).await;
+    assert_eq!(status, 200);
+    assert_eq!(detail["id"], 7001);
+    assert_eq!(detail["title"], "作品");
+    assert_eq!(detail["tags"][0], "标签");
+
+    let (status, images) = get(cfg.clone(), "/api/images/7001", key).await;
+    assert_eq!(status, 200);
+    assert_eq!(images["total"], 2);
+    assert_eq!(images["images"][1], "https://img.example/2.webp");
+
+    let (status, _) = get(cfg, "/api/search", key).await;
+    assert_eq!(status, 400);
+}
+
+#[actix_web::test]
+async fn test_api_jobs() {
+    let cfg = setup().await;
+    let (status, list) = get(cfg, "/api/jobs", Some(("X-Api-Key", "secret-key"))).await;
+    assert_eq!(status, 200);
+    let job = list.as_array().unwrap().iter().find(|j| j["aid"] == 7001).unwrap();
+    assert_eq!(job["status"], "queued");
+    assert_eq!(job["total"], 3);
+}
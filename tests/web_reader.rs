@@ -0,0 +1,145 @@
+mod common;
+
+use actix_web::{App, test, web};
+use mangabot_rs::config::Config;
+use mangabot_rs::models::MangaDetail;
+use mangabot_rs::services::links::{self, ReaderLink};
+use mangabot_rs::services::proxy;
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::utils::{cache, escape_xml};
+use std::sync::OnceLock;
+
+/// 页面中转义后的代理图片地址
+fn img_src(url: &str) -> String {
+    escape_xml(&proxy::proxy_url(url))
+}
+
+static INIT: OnceLock<()> = OnceLock::new();
+
+async fn setup(aid: i64) -> Config {
+    let cfg = Config::load().unwrap();
+    INIT.get_or_init(|| {
+        cache::init(&cfg).unwrap();
+        links::init(&cfg).unwrap();
+        // 图片代理只允许 img.example
+        let mut proxy_cfg = cfg.clone();
+        proxy_cfg.manga.base_url = "https://img.example".to_string();
+        proxy_cfg.server.image_cache_path =
+            tempfile::tempdir().unwrap().keep().to_string_lossy().to_string();
+        proxy::init(&proxy_cfg).unwrap();
+    });
+    let detail = MangaDetail {
+        author: "作者".to_string(),
+        category: "同人".to_string(),
+        tags: vec!["标签".to_string()],
+        ..common::detail(aid, "作品 <A>", 3)
+    };
+    cache::info_cache().insert(aid.to_string(), detail).await;
+    let images = (1..=3).map(|i| format!("https://img.example/{}/{}.webp", aid, i)).collect();
+    cache::image_cache().insert(aid.to_string(), images).await;
+    cfg
+}
+
+async fn get(cfg: &Config, uri: &str) -> (u16, String) {
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(cfg.clone())).configure(web_configure),
+    )
+    .await;
+    let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+    let status = resp.status().as_u16();
+    let body = test::read_body(resp).await;
+    (status, String::from_utf8_lossy(&body).to_string())
+}
+
+#[actix_web::test]
+async fn test_reader_scroll_mode() {
+    let cfg = setup(9001).await;
+    let url = links::reader_url(&cfg, 9001);
+    let path = url.trim_start_matches(cfg.server.web_host.trim_end_matches('/'));
+    assert!(path.starts_with("/read/9001?exp="));
+
+    let (status, body) = get(&cfg, path).await;
+    assert_eq!(status, 200);
+    assert!(body.contains("<h1>作品 &lt;A&gt;</h1>"));
+    assert!(body.contains("#标签"));
+    for i in 1..=3 {
+        let url = format!("https://img.example/9001/{}.webp", i);
+        assert!(body.contains(&img_src(&url)));
+    }
+}
+
+#[actix_web::test]
+async fn test_reader_paged_mode() {
+    let cfg = setup(9002).await;
+    let exp = chrono::Utc::now().timestamp() + 60;
+    let link = ReaderLink::new(links::store().secret(), 9002, exp);
+
+    let (status, body) = get(&cfg, &format!("/read/9002?{}&mode=paged&page=2", link.query())).await;
+    assert_eq!(status, 200);
+    assert!(body.contains(&img_src("https://img.example/9002/2.webp")));
+    assert!(!body.contains(&img_src("https://img.example/9002/1.webp")));
+    assert!(body.contains("2 / 3"));
+    assert!(body.contains("page=1") && body.contains("page=3"));
+
+    // 页码越界时取最后一页
+    let (_, body) = get(&cfg, &format!("/read/9002?{}&mode=paged&page=99", link.query())).await;
+    assert!(body.contains("3 / 3"));
+    assert!(!body.contains("id=\"next\""));
+}
+
+#[actix_web::test]
+async fn test_reader_links_disallowed_hosts_directly() {
+    let cfg = setup(9004).await;
+    let images = vec![
+        "https://img.example/9004/1.webp".to_string(),
+        "https://cdn.other.example/9004/2.webp".to_string(),
+    ];
+    cache::image_cache().insert("9004".to_string(), images).await;
+    let exp = chrono::Utc::now().timestamp() + 60;
+    let link = ReaderLink::new(links::store().secret(), 9004, exp);
+
+    // 代理会拒绝不在允许列表中的主机，这类页面直接引用原地址
+    let (status, body) = get(&cfg, &format!("/read/9004?{}", link.query())).await;
+    assert_eq!(status, 200);
+    assert!(body.contains(&img_src("https://img.example/9004/1.webp")));
+    assert!(body.contains("src=\"https://cdn.other.example/9004/2.webp\""));
+    assert!(!body.contains(&img_src("https://cdn.other.example/9004/2.webp")));
+}
+
+#[actix_web::test]
+async fn test_reader_rejects_bad_links() {
+    let cfg = setup(9003).await;
+    let secret = links::store().secret();
+    let now = chrono::Utc::now().timestamp();
+
+    // 签名绑定作品 id
+    let other = ReaderLink::new(secret, 9002, now + 60);
+    assert_eq!(get(&cfg, &format!("/read/9003?{}", other.query())).await.0, 403);
+
+    let expired = ReaderLink::new(secret, 9003, now - 1);
+    assert_eq!(get(&cfg, &format!("/read/9003?{}", expired.query())).await.0, 410);
+
+    // 下载链接的签名不能用于阅读
+    let download = links::SignedLink::new(secret, "9003", now + 60, 0);
+    let uri = format!("/read/9003?exp={}&sig={}", download.exp, download.sig);
+    assert_eq!(get(&cfg, &uri).await.0, 403);
+}
+
+#[actix_web::test]
+async fn test_web_host_public() {
+    let mut cfg = Config::load().unwrap();
+    for (host, public) in [
+        ("http://localhost:8087", false),
+        ("http://LOCALHOST./", false),
+        ("http://app.localhost", false),
+        ("http://127.0.0.1:8087", false),
+        ("http://0.0.0.0:8087", false),
+        ("http://[::1]:8087", false),
+        ("not a url", false),
+        ("https://manga.example.com", true),
+        ("http://192.168.1.2:8087", true),
+    ] {
+        cfg.server.web_host = host.to_string();
+        assert_eq!(links::web_host_public(&cfg), public, "{host}");
+    }
+}
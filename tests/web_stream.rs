@@ -0,0 +1,58 @@
+mod common;
+
+use actix_web::{App, test, web};
+use common::{Reply, encode, serve};
+use mangabot_rs::config::Config;
+use mangabot_rs::models::ArchiveStream;
+use mangabot_rs::services::web::configure as web_configure;
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+#[actix_web::test]
+async fn test_stream_token_serves_zip_on_the_fly() {
+    let cfg = Config::load().unwrap();
+    mangabot_rs::utils::cache::init(&cfg).unwrap();
+    mangabot_rs::utils::client::init(&cfg).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    // /1 返回 JPEG，/2 返回 PNG，/3 返回 HTML 错误页，其余 404
+    tokio::spawn(serve(listener, |path| match path {
+        "/1" => Reply::ok(encode(image::ImageFormat::Jpeg)),
+        "/2" => Reply::ok(encode(image::ImageFormat::Png)),
+        "/3" => {
+            Reply::ok(b"<!DOCTYPE html><html><body>503 Service Unavailable</body></html>".to_vec())
+        }
+        _ => Reply::not_found(),
+    }));
+
+    let token = Uuid::new_v4().to_string();
+    let images = vec![
+        format!("{}/1", base),
+        format!("{}/404", base),
+        format!("{}/2", base),
+        format!("{}/3", base),
+    ];
+    mangabot_rs::utils::cache::stream_token_cache()
+        .insert(token.clone(), ArchiveStream { title: "t".to_string(), images })
+        .await;
+
+    let app =
+        test::init_service(App::new().app_data(web::Data::new(cfg)).configure(web_configure)).await;
+    let req = test::TestRequest::get().uri(&format!("/download?token={}", token)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/zip");
+
+    let body = test::read_body(resp).await;
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body.to_vec())).unwrap();
+    // 页面按页序命名；抓取失败的第 2 页与校验不通过的第 4 页以说明文件占位
+    assert_eq!(archive.len(), 4);
+    assert!(archive.by_name("0001.jpg").is_ok());
+    assert!(archive.by_name("0003.png").is_ok());
+    let mut note = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("0002.missing.txt").unwrap(), &mut note)
+        .unwrap();
+    assert!(note.contains(&format!("{}/404", base)));
+    assert!(archive.by_name("0004.missing.txt").is_ok());
+}
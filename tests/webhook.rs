@@ -0,0 +1,64 @@
+use actix_web::{App, test, web};
+use futures::StreamExt;
+use mangabot_rs::config::Config;
+use mangabot_rs::services::web::configure as web_configure;
+use mangabot_rs::services::webhook::{self, SECRET_HEADER, UpdateMode};
+use teloxide::update_listeners::{AsUpdateStream, UpdateListener};
+
+const UPDATE: &str = r#"{"update_id":42,"message":{"message_id":1,"date":0,
+"chat":{"id":7,"type":"private","first_name":"a"},
+"from":{"id":7,"is_bot":false,"first_name":"a"},"text":"/help"}}"#;
+
+async fn post(cfg: &Config, secret: Option<&str>) -> u16 {
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(cfg.clone())).configure(web_configure),
+    )
+    .await;
+    let mut req = test::TestRequest::post().uri("/webhook").set_payload(UPDATE);
+    if let Some(secret) = secret {
+        req = req.insert_header((SECRET_HEADER, secret));
+    }
+    test::call_service(&app, req.to_request()).await.status().as_u16()
+}
+
+#[actix_web::test]
+async fn test_polling_is_default() {
+    let cfg = Config::load().unwrap();
+    assert_eq!(cfg.bot.update_mode, UpdateMode::Polling);
+}
+
+#[actix_web::test]
+async fn test_secret_derived_from_token_when_unset() {
+    let mut cfg = Config::load().unwrap();
+    cfg.bot.telegram_token = "123:abc".to_string();
+    let derived = webhook::secret(&cfg);
+    assert_eq!(derived.len(), 64);
+    assert!(derived.bytes().all(|b| b.is_ascii_alphanumeric()));
+    cfg.bot.webhook_secret = "my_secret-1".to_string();
+    assert_eq!(webhook::secret(&cfg), "my_secret-1");
+}
+
+#[actix_web::test]
+async fn test_webhook_delivers_updates_to_listener() {
+    let mut cfg = Config::load().unwrap();
+    cfg.bot.webhook_secret = "s3cret".to_string();
+
+    assert_eq!(post(&cfg, None).await, 401);
+    assert_eq!(post(&cfg, Some("wrong")).await, 401);
+    // 监听器未创建（轮询模式）
+    assert_eq!(post(&cfg, Some("s3cret")).await, 503);
+
+    let mut listener = webhook::listener().unwrap();
+    assert!(webhook::listener().is_err());
+    assert_eq!(post(&cfg, Some("s3cret")).await, 200);
+    let stop = listener.stop_token();
+    {
+        let mut stream = std::pin::pin!(listener.as_stream());
+        let update = stream.next().await.unwrap().unwrap();
+        assert_eq!(update.id.0, 42);
+    }
+
+    stop.stop();
+    assert_eq!(post(&cfg, Some("s3cret")).await, 503);
+    assert!(std::pin::pin!(listener.as_stream()).next().await.is_none());
+}
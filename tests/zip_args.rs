@@ -0,0 +1,59 @@
+use mangabot_rs::bot::commands::{Command, ZipArgs};
+use teloxide::utils::command::BotCommands;
+
+fn parse(text: &str) -> ZipArgs {
+    match Command::parse(text, "mangars_bot").unwrap() {
+        Command::Zip(args) => args,
+        other => panic!("unexpected command: {:?}", other),
+    }
+}
+
+#[test]
+fn test_zip_args_page_range() {
+    let args = parse("/zip 123 1-20,35 cbz");
+    assert_eq!(args.aids, vec![123]);
+    assert_eq!(args.pages.as_deref(), Some("1-20,35"));
+    assert_eq!(args.format.as_deref(), Some("cbz"));
+    assert_eq!(args.transcode, None);
+}
+
+#[test]
+fn test_zip_args_multiple_works() {
+    let args = parse("/zip 1 2 3 jpeg merge");
+    assert_eq!(args.aids, vec![1, 2, 3]);
+    assert_eq!(args.transcode.as_deref(), Some("jpeg"));
+    assert!(args.combine);
+    assert_eq!(args.pages, None);
+
+    // 单页需要带 p 前缀，否则视为 aid
+    assert_eq!(parse("/zip 1 p35").pages.as_deref(), Some("p35"));
+    assert_eq!(parse("/zip 1 35").aids, vec![1, 35]);
+}
+
+#[test]
+fn test_overlong_button_is_an_error() {
+    let pages = "1,3,5,7,9,11,13,15,17,19,21,23,25";
+    let args = ["123456".to_string(), "zip".to_string(), "original".to_string(), pages.to_string()];
+    assert!(mangabot_rs::utils::codec::encode_command_button("🔁重试", "zip", &args).is_err());
+}
+
+#[tokio::test]
+async fn test_retry_button_restores_zip_args_from_cache() {
+    use mangabot_rs::utils::{cache, codec};
+    cache::init(&mangabot_rs::config::Config::load().unwrap()).unwrap();
+
+    let pages = "1,3,5,7,9,11,13,15,17,19,21,23,25";
+    let args = vec!["123456".to_string(), "cbz".to_string(), "jpeg".to_string(), pages.to_string()];
+    let num = cache::retry_to_num(args).await;
+    let payload = codec::encode_command("rezip", &[num.to_string()]).unwrap();
+    match codec::decode_command(&payload).await.unwrap() {
+        Command::Zip(args) => {
+            assert_eq!(args.aids, vec![123456]);
+            assert_eq!(args.format.as_deref(), Some("cbz"));
+            assert_eq!(args.transcode.as_deref(), Some("jpeg"));
+            assert_eq!(args.pages.as_deref(), Some(pages));
+            assert!(!args.combine);
+        }
+        other => panic!("unexpected command: {:?}", other),
+    }
+}